@@ -0,0 +1,156 @@
+//! Reproduces the benchmark tables of the README.
+//!
+//! Every fixture is counted with every combination of the search features,
+//! and the number of states searched is compared to the recorded one so that
+//! pruning regressions get noticed. Run it with `cargo bench`, optionally
+//! followed by `-- <name>` to only run the fixtures whose name contains `<name>`.
+
+use std::{process::ExitCode, time::Instant};
+
+use witness_solver::{puzzle::*, solver::BFSSolver, solver::BFSSolverConfig};
+
+/// Search features that can be toggled, in the order of the bits of a config index
+const FEATURES: [&str; 5] = [
+    "End reachability",
+    "Edge stones",
+    "Partial areas",
+    "Closed areas",
+    "Transpositions",
+];
+const CONFIGS: usize = 1 << FEATURES.len();
+
+struct Fixture {
+    name: &'static str,
+    puzzle: Puzzle,
+    solutions: u128,
+    /// Number of states searched with every config
+    states: [u64; CONFIGS],
+}
+
+fn config(idx: usize) -> BFSSolverConfig {
+    BFSSolverConfig {
+        simple_end_reachability_check: idx & 1 != 0,
+        edge_stones: idx & 2 != 0,
+        partial_area_check: idx & 4 != 0,
+        closed_area_check: idx & 8 != 0,
+        merge_transpositions: idx & 16 != 0,
+        max_queue_size: 0,
+        ..Default::default()
+    }
+}
+
+fn fixtures() -> Vec<Fixture> {
+    // Squares of the partial area benchmark, from the top row to the bottom one
+    let squares = ["BBBBB", "BBWBB", "BWWWB", "WWWWW", "BWWWB"];
+
+    vec![
+        Fixture {
+            name: "Empty 5x5",
+            puzzle: Puzzle::default_with_size(5, 5),
+            solutions: 1262816,
+            states: [
+                31811177, 17207595, 31811177, 17207595, 31811177, 17207595, 31811177, 17207595,
+                31811177, 17207595, 31811177, 17207595, 31811177, 17207595, 31811177, 17207595,
+                724179, 511039, 724179, 511039, 724179, 511039, 724179, 511039, 724179, 511039,
+                724179, 511039, 724179, 511039, 724179, 511039,
+            ],
+        },
+        Fixture {
+            name: "Early stone check",
+            puzzle: Puzzle {
+                edge_stones: [EdgePos::new(2, 2, Direction::Up)].into(),
+                ..Puzzle::default_with_size(5, 5)
+            },
+            solutions: 518087,
+            states: [
+                31811177, 17207595, 15108640, 8197554, 31811177, 17207595, 15108640, 8197554,
+                22668368, 12412793, 14186762, 7700624, 22668368, 12412793, 14186762, 7700624,
+                807479, 575061, 469195, 330529, 807479, 575061, 469195, 330529, 770050, 546743,
+                460023, 323539, 770050, 546743, 460023, 323539,
+            ],
+        },
+        Fixture {
+            name: "Partial area check",
+            puzzle: Puzzle {
+                squares: squares
+                    .iter()
+                    .rev()
+                    .enumerate()
+                    .flat_map(|(y, row)| {
+                        row.chars().enumerate().map(move |(x, c)| {
+                            (Pos::new(x as Coord, y as Coord), (c == 'W') as Color)
+                        })
+                    })
+                    .collect(),
+                ..Puzzle::default_with_size(5, 5)
+            },
+            solutions: 12,
+            states: [
+                31811177, 17207595, 31811177, 17207595, 301, 256, 301, 256, 3237340, 1787134,
+                3237340, 1787134, 267, 237, 267, 237, 31811177, 17207595, 31811177, 17207595, 301,
+                256, 301, 256, 3237328, 1787131, 3237328, 1787131, 255, 234, 255, 234,
+            ],
+        },
+    ]
+}
+
+fn main() -> ExitCode {
+    let filter = std::env::args()
+        .skip(1)
+        .find(|arg| !arg.starts_with('-'))
+        .unwrap_or_default();
+    let mut failures = vec![];
+
+    for fixture in fixtures() {
+        if !fixture.name.contains(&filter) {
+            continue;
+        }
+
+        println!("### {}", fixture.name);
+        println!();
+        println!(
+            "| {} | Number of states searched | Time |",
+            FEATURES.join(" | ")
+        );
+        println!("|{}---|---|", "---|".repeat(FEATURES.len()));
+
+        let mut states = [0; CONFIGS];
+        for (idx, count) in states.iter_mut().enumerate() {
+            let mut solver = BFSSolver::new(&fixture.puzzle).unwrap();
+            solver.config = config(idx);
+
+            let before = Instant::now();
+            let solutions = solver.count().count;
+            let time = before.elapsed();
+            *count = solver.stats.states_visited;
+
+            let enabled = (0..FEATURES.len())
+                .map(|bit| if idx & (1 << bit) != 0 { "x" } else { " " })
+                .collect::<Vec<_>>();
+            println!("| {} | {} | {:.1?} |", enabled.join(" | "), count, time);
+
+            if solutions != fixture.solutions {
+                failures.push(format!(
+                    "{}, config {idx}: found {solutions} solutions instead of {}",
+                    fixture.name, fixture.solutions
+                ));
+            }
+        }
+        println!();
+
+        if states != fixture.states {
+            failures.push(format!(
+                "{}: the number of states searched changed, they are now {:?}",
+                fixture.name, states
+            ));
+        }
+    }
+
+    if failures.is_empty() {
+        return ExitCode::SUCCESS;
+    }
+    for failure in failures {
+        eprintln!("{failure}");
+    }
+    ExitCode::FAILURE
+}
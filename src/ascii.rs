@@ -0,0 +1,650 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Display,
+    str::FromStr,
+};
+
+use crate::puzzle::*;
+
+// Text format of a puzzle, with the top of the puzzle first:
+//
+//     +---+---E
+//     |*a*|[b]|
+//     S---+   +
+//
+// Vertices are two rows apart, and four columns apart so that cells
+// are three characters wide.
+//
+// Vertices:  `+`, `S` start, `E` end, `X` start and end, `o` stone.
+//            `s`, `e` and `x` are a start, end, or both with a stone.
+// Edges:     `---` and `|`, `-o-` and `o` with a stone, blank when broken
+// Cells:     `[a]` square, `*a*` star, `\a/` canceller, `^` to `^^^`
+//            triangles, `P1` polyomino and `N1` negative polyomino,
+//            `///` outside of the puzzle. Letters are colours and numbers
+//            refer to the shapes listed below the grid.
+// Path:      `#` on vertices and edges, crossed stones become `O`
+//
+// Shapes are listed after an empty line, as a `P1` or `N1 rotatable`
+// header followed by the rows of the shape, with `#` for blocks and `.`
+// for holes. Only puzzles without a path can be parsed back.
+//
+// Labels must fit in a cell, so puzzles with colours past `z`, more
+// than 99 polyominoes or more than 3 triangles in a cell can't be drawn.
+
+/// Characters per cell, horizontally
+const CELL_WIDTH: usize = 4;
+
+/// Most polyominoes that can be numbered in a cell
+const MAX_SHAPES: usize = 99;
+
+/// Letter used for a colour
+fn color_letter(color: Color) -> Result<char, String> {
+    if color < 26 {
+        Ok((b'a' + color) as char)
+    } else {
+        Err(format!(
+            "colour {color} has no letter, only 26 colours can be drawn"
+        ))
+    }
+}
+
+/// Centers the text in a cell
+fn cell_text(text: &str) -> String {
+    format!("{:^3}", text)
+}
+
+/// Grid of characters, indexed by puzzle coordinates
+struct Canvas {
+    rows: Vec<Vec<char>>,
+    height: Coord,
+}
+
+impl Canvas {
+    fn new(puzzle: &Puzzle) -> Self {
+        let width = puzzle.width as usize * CELL_WIDTH + 1;
+        let height = puzzle.height as usize * 2 + 1;
+        Self {
+            rows: vec![vec![' '; width]; height],
+            height: puzzle.height,
+        }
+    }
+
+    /// Row and column of a vertex
+    fn vertex_index(&self, pos: &Pos) -> (usize, usize) {
+        (
+            (self.height - pos.y) as usize * 2,
+            pos.x as usize * CELL_WIDTH,
+        )
+    }
+
+    fn set_vertex(&mut self, pos: &Pos, c: char) {
+        let (row, col) = self.vertex_index(pos);
+        self.rows[row][col] = c;
+    }
+
+    fn set_edge(&mut self, edge: &EdgePos, text: &str) {
+        let edge = edge.normalize();
+        let (row, col) = self.vertex_index(&edge.pos);
+        match edge.dir {
+            Direction::Right => self.write(row, col + 1, text),
+            _ => self.write(row - 1, col, &text[1..2]),
+        }
+    }
+
+    /// Fails if the text doesn't fit in the cell
+    fn set_cell(&mut self, pos: &Pos, text: &str) -> Result<(), String> {
+        if text.chars().count() > CELL_WIDTH - 1 {
+            return Err(format!("{text:?} doesn't fit in cell {pos}"));
+        }
+        let (row, col) = self.vertex_index(pos);
+        self.write(row - 1, col + 1, text);
+        Ok(())
+    }
+
+    fn write(&mut self, row: usize, col: usize, text: &str) {
+        for (idx, c) in text.chars().enumerate() {
+            self.rows[row][col + idx] = c;
+        }
+    }
+}
+
+impl Display for Canvas {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for row in &self.rows {
+            writeln!(f, "{}", row.iter().collect::<String>().trim_end())?;
+        }
+        Ok(())
+    }
+}
+
+/// Draws the puzzle as text, with the path over it if there is one.
+///
+/// Polyominoes don't fit in a cell: they are numbered, and their shapes
+/// are drawn below the grid. Fails if a label doesn't fit in its cell.
+pub fn render(puzzle: &Puzzle, path: Option<&SolutionPath>) -> Result<String, String> {
+    let mut canvas = Canvas::new(puzzle);
+
+    let mut path_vertices = HashSet::new();
+    let mut path_edges = HashSet::new();
+    if let Some(path) = path {
+        path_vertices.extend(path.iter().copied());
+        for win in path.windows(2) {
+            if let Some(dir) = win[0].get_direction_to(&win[1]) {
+                path_edges.insert(EdgePos::new(win[0].x, win[0].y, dir));
+            }
+        }
+    }
+
+    // Edges, going up and right from every vertex
+    for x in 0..=puzzle.width {
+        for y in 0..=puzzle.height {
+            for dir in [Direction::Up, Direction::Right] {
+                let edge = EdgePos::new(x, y, dir);
+                let [_, other] = edge.get_neighbouring_corners();
+                if !puzzle.contains_vertex(&other) || puzzle.is_blocked(&edge) {
+                    continue;
+                }
+
+                let on_path = path_edges.contains(&edge);
+                let text = match (puzzle.edge_stones.contains(&edge), on_path) {
+                    (true, true) => "#O#",
+                    (true, false) => "-o-",
+                    (false, true) => "###",
+                    (false, false) if dir == Direction::Up => " | ",
+                    (false, false) => "---",
+                };
+                canvas.set_edge(&edge, text);
+            }
+        }
+    }
+
+    // Vertices, hidden when all their edges are broken
+    for x in 0..=puzzle.width {
+        for y in 0..=puzzle.height {
+            let pos = Pos::new(x, y);
+            let connected = Direction::VARIANTS.iter().any(|dir| {
+                puzzle.contains_vertex(&pos.move_direction(*dir))
+                    && !puzzle.is_blocked(&EdgePos::new(x, y, *dir))
+            });
+            let on_path = path_vertices.contains(&pos);
+
+            let stone = puzzle.vertex_stones.contains(&pos);
+            let c = match (puzzle.starts.contains(&pos), puzzle.ends.contains(&pos)) {
+                (true, true) if stone => 'x',
+                (true, true) => 'X',
+                (true, false) if stone => 's',
+                (true, false) => 'S',
+                (false, true) if stone => 'e',
+                (false, true) => 'E',
+                _ if stone && on_path => 'O',
+                _ if stone => 'o',
+                _ if on_path => '#',
+                _ if connected => '+',
+                _ => ' ',
+            };
+            canvas.set_vertex(&pos, c);
+        }
+    }
+
+    // Cells, from the top left so that polyominoes are numbered in reading order
+    let mut shapes = vec![];
+    for y in (0..puzzle.height).rev() {
+        for x in 0..puzzle.width {
+            let pos = Pos::new(x, y);
+            let text = if !puzzle.contains_cell(&pos) {
+                "///".to_string()
+            } else if let Some(color) = puzzle.squares.get(&pos) {
+                format!("[{}]", color_letter(*color)?)
+            } else if let Some(color) = puzzle.stars.get(&pos) {
+                format!("*{}*", color_letter(*color)?)
+            } else if let Some(color) = puzzle.cancels.get(&pos) {
+                format!("\\{}/", color_letter(*color)?)
+            } else if let Some(&count) = puzzle.triangles.get(&pos) {
+                if !(1..=3).contains(&count) {
+                    return Err(PuzzleError::InvalidTriangleCount(pos, count).to_string());
+                }
+                cell_text(&"^".repeat(count as usize))
+            } else if let Some(poly) = puzzle.polys.get(&pos) {
+                shapes.push(('P', poly));
+                cell_text(&format!("P{}", shapes.len()))
+            } else if let Some(poly) = puzzle.ylops.get(&pos) {
+                shapes.push(('N', poly));
+                cell_text(&format!("N{}", shapes.len()))
+            } else {
+                continue;
+            };
+            // Too many polyominoes are reported below, with their count
+            if shapes.len() <= MAX_SHAPES {
+                canvas.set_cell(&pos, &text)?;
+            }
+        }
+    }
+    if shapes.len() > MAX_SHAPES {
+        return Err(format!(
+            "{} polyominoes can't be numbered, the most is {MAX_SHAPES}",
+            shapes.len()
+        ));
+    }
+
+    let mut res = canvas.to_string();
+    for (idx, (letter, poly)) in shapes.iter().enumerate() {
+        res.push_str(&format!("\n{letter}{}", idx + 1));
+        if poly.rotatable {
+            res.push_str(" rotatable");
+        }
+        res.push('\n');
+        res.push_str(&render_poly(poly));
+    }
+
+    Ok(res)
+}
+
+/// Draws the shape of a polyomino with `#` blocks, top row first
+fn render_poly(poly: &Poly) -> String {
+    if poly.minos.is_empty() {
+        return String::new();
+    }
+
+    let min_x = poly.minos.iter().map(|p| p.x).min().unwrap();
+    let max_x = poly.minos.iter().map(|p| p.x).max().unwrap();
+    let min_y = poly.minos.iter().map(|p| p.y).min().unwrap();
+    let max_y = poly.minos.iter().map(|p| p.y).max().unwrap();
+
+    let mut res = String::new();
+    for y in (min_y..=max_y).rev() {
+        let row: String = (min_x..=max_x)
+            .map(|x| {
+                if poly.minos.contains(&Pos::new(x, y)) {
+                    '#'
+                } else {
+                    '.'
+                }
+            })
+            .collect();
+        res.push_str(&row);
+        res.push('\n');
+    }
+    res
+}
+
+/// Reads a puzzle in the format produced by [`render`], without a path
+pub fn parse(src: &str) -> Result<Puzzle, String> {
+    let lines: Vec<(usize, &str)> = src
+        .lines()
+        .enumerate()
+        .map(|(idx, line)| (idx + 1, line.trim_end()))
+        .skip_while(|(_, line)| line.is_empty())
+        .collect();
+    let grid_len = lines
+        .iter()
+        .position(|(_, line)| line.is_empty())
+        .unwrap_or(lines.len());
+    let (grid, legend) = lines.split_at(grid_len);
+
+    let max_len = grid.iter().map(|(_, line)| line.len()).max().unwrap_or(0);
+    if grid.len() < 3 || grid.len() % 2 == 0 || max_len < CELL_WIDTH + 1 {
+        return Err("The grid needs an odd number of rows, and at least one cell".to_string());
+    }
+    let width = (max_len - 1).div_ceil(CELL_WIDTH);
+    let height = grid.len() / 2;
+    if width > Puzzle::MAX_SIZE as usize || height > Puzzle::MAX_SIZE as usize {
+        return Err(format!(
+            "Puzzle is too large ({width}x{height}, max is {})",
+            Puzzle::MAX_SIZE
+        ));
+    }
+
+    let mut puzzle = Puzzle {
+        width: width as Coord,
+        height: height as Coord,
+        starts: vec![],
+        ends: vec![],
+        ..Default::default()
+    };
+    let canvas = Canvas {
+        rows: grid
+            .iter()
+            .map(|(_, line)| {
+                format!("{line:<0$}", width * CELL_WIDTH + 1)
+                    .chars()
+                    .collect()
+            })
+            .collect(),
+        height: puzzle.height,
+    };
+    let error = |row: usize, message: String| format!("line {}: {message}", grid[row].0);
+
+    // Vertices and the edges going up and right from them
+    for x in 0..=puzzle.width {
+        for y in 0..=puzzle.height {
+            let pos = Pos::new(x, y);
+            let (row, col) = canvas.vertex_index(&pos);
+            let c = canvas.rows[row][col];
+            let (start, end, stone) = match c {
+                '+' | ' ' => (false, false, false),
+                'S' | 's' => (true, false, c == 's'),
+                'E' | 'e' => (false, true, c == 'e'),
+                'X' | 'x' => (true, true, c == 'x'),
+                'o' => (false, false, true),
+                _ => return Err(error(row, format!("unexpected {c:?} on a vertex"))),
+            };
+            if start {
+                puzzle.starts.push(pos);
+            }
+            if end {
+                puzzle.ends.push(pos);
+            }
+            if stone {
+                puzzle.vertex_stones.insert(pos);
+            }
+
+            if x < puzzle.width {
+                let text: String = canvas.rows[row][col + 1..col + CELL_WIDTH].iter().collect();
+                let edge = EdgePos::new(x, y, Direction::Right);
+                match text.as_str() {
+                    "---" => {}
+                    "-o-" => _ = puzzle.edge_stones.insert(edge),
+                    "   " => _ = puzzle.blocked_edges.insert(edge),
+                    _ => return Err(error(row, format!("unexpected {text:?} on an edge"))),
+                }
+            }
+            if y < puzzle.height {
+                let c = canvas.rows[row - 1][col];
+                let edge = EdgePos::new(x, y, Direction::Up);
+                match c {
+                    '|' => {}
+                    'o' => _ = puzzle.edge_stones.insert(edge),
+                    ' ' => _ = puzzle.blocked_edges.insert(edge),
+                    _ => return Err(error(row - 1, format!("unexpected {c:?} on an edge"))),
+                }
+            }
+        }
+    }
+
+    let shapes = parse_shapes(legend)?;
+
+    // Cells
+    for x in 0..puzzle.width {
+        for y in 0..puzzle.height {
+            let pos = Pos::new(x, y);
+            let (row, col) = canvas.vertex_index(&pos);
+            let text: String = canvas.rows[row - 1][col + 1..col + CELL_WIDTH]
+                .iter()
+                .collect();
+            let chars: Vec<char> = text.trim().chars().collect();
+            let color = |c: char| {
+                c.is_ascii_lowercase()
+                    .then(|| c as u8 - b'a')
+                    .ok_or_else(|| error(row - 1, format!("invalid colour {c:?}")))
+            };
+
+            match chars.as_slice() {
+                [] => {}
+                ['/', '/', '/'] => _ = puzzle.outside_positions.insert(pos),
+                ['[', c, ']'] => _ = puzzle.squares.insert(pos, color(*c)?),
+                ['*', c, '*'] => _ = puzzle.stars.insert(pos, color(*c)?),
+                ['\\', c, '/'] => _ = puzzle.cancels.insert(pos, color(*c)?),
+                ['^'] | ['^', '^'] | ['^', '^', '^'] => {
+                    _ = puzzle.triangles.insert(pos, chars.len() as u8)
+                }
+                ['P' | 'N', ..] => {
+                    let label = text.trim();
+                    let poly = shapes
+                        .get(label)
+                        .ok_or_else(|| error(row - 1, format!("no shape for {label}")))?
+                        .clone();
+                    if chars[0] == 'P' {
+                        puzzle.polys.insert(pos, poly);
+                    } else {
+                        puzzle.ylops.insert(pos, poly);
+                    }
+                }
+                _ => return Err(error(row - 1, format!("unexpected {text:?} in a cell"))),
+            }
+        }
+    }
+
+    puzzle.validate_structure().map_err(|errors| {
+        errors
+            .iter()
+            .map(PuzzleError::to_string)
+            .collect::<Vec<_>>()
+            .join(", ")
+    })?;
+
+    Ok(puzzle)
+}
+
+/// Reads the shapes listed below the grid, by label
+fn parse_shapes(lines: &[(usize, &str)]) -> Result<HashMap<String, Poly>, String> {
+    let mut shapes = HashMap::new();
+    let mut current: Option<(String, bool, Vec<&str>)> = None;
+
+    let mut finish = |current: Option<(String, bool, Vec<&str>)>| {
+        if let Some((label, rotatable, rows)) = current {
+            // Same order as `Poly::from`, column by column from the bottom left
+            let mut minos = vec![];
+            let width = rows.iter().map(|row| row.len()).max().unwrap_or(0);
+            for x in 0..width {
+                for (y, row) in rows.iter().rev().enumerate() {
+                    if row.as_bytes().get(x) == Some(&b'#') {
+                        minos.push(Pos::new(x as Coord, y as Coord));
+                    }
+                }
+            }
+            shapes.insert(label, Poly { rotatable, minos });
+        }
+    };
+
+    for &(line_nb, line) in lines {
+        if line.is_empty() {
+            continue;
+        }
+
+        if line.starts_with(['P', 'N']) {
+            let (label, rotatable) = match line.split_once(' ') {
+                Some((label, "rotatable")) => (label, true),
+                None => (line, false),
+                Some(_) => return Err(format!("line {line_nb}: invalid shape header {line:?}")),
+            };
+            finish(current.replace((label.to_string(), rotatable, vec![])));
+        } else if let Some((_, _, rows)) = &mut current {
+            if !line.chars().all(|c| c == '#' || c == '.') {
+                return Err(format!("line {line_nb}: invalid shape row {line:?}"));
+            }
+            rows.push(line);
+        } else {
+            return Err(format!("line {line_nb}: expected a shape header"));
+        }
+    }
+    finish(current);
+
+    Ok(shapes)
+}
+
+impl FromStr for Puzzle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse(s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_symbols() {
+        let mut puzzle = Puzzle::default_with_size(3, 2);
+        puzzle.ends = vec![Pos::new(3, 2)];
+        puzzle.squares.insert(Pos::new(0, 1), 0);
+        puzzle.stars.insert(Pos::new(1, 1), 1);
+        puzzle.cancels.insert(Pos::new(2, 1), 1);
+        puzzle.triangles.insert(Pos::new(0, 0), 2);
+        puzzle
+            .polys
+            .insert(Pos::new(1, 0), Poly::from(0x8C00 | 1 << 20));
+        puzzle.outside_positions.insert(Pos::new(2, 0));
+        puzzle
+            .blocked_edges
+            .insert(EdgePos::new(1, 2, Direction::Right));
+        puzzle
+            .blocked_edges
+            .insert(EdgePos::new(3, 0, Direction::Up));
+        puzzle.vertex_stones.insert(Pos::new(1, 1));
+        puzzle.edge_stones.insert(EdgePos::new(0, 1, Direction::Up));
+
+        let expected = "\
++---+   +---E
+o[a]|*b*|\\b/|
++---o---+---+
+|^^ |P1 |///
+S---+---+---+
+
+P1 rotatable
+#.
+##
+";
+        assert_eq!(render(&puzzle, None).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_render_path() {
+        let mut puzzle = Puzzle::default_with_size(2, 2);
+        puzzle.vertex_stones.insert(Pos::new(1, 1));
+        puzzle.edge_stones.insert(EdgePos::new(2, 1, Direction::Up));
+        let path = SolutionPath::new(Pos::new(0, 0), "URRU".to_string()).unwrap();
+
+        let expected = "\
++---+---E
+|   |   O
+####O####
+#   |   |
+S---+---+
+";
+        assert_eq!(render(&puzzle, Some(&path)).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_parse() {
+        let src = r"
+x---+   +---E---+
+o[a]|*b*|\b/|   |
++---o---+---+---+
+|^^ |P1 |/// N2 |
+s---+-o-+---+---+
+
+P1 rotatable
+#.
+##
+
+N2
+#
+";
+        let puzzle = parse(src).unwrap();
+        assert_eq!((puzzle.width, puzzle.height), (4, 2));
+        assert_eq!(puzzle.starts, vec![Pos::new(0, 0), Pos::new(0, 2)]);
+        assert_eq!(puzzle.ends, vec![Pos::new(0, 2), Pos::new(3, 2)]);
+        assert_eq!(puzzle.vertex_stones.len(), 3);
+        assert!(puzzle
+            .edge_stones
+            .contains(&EdgePos::new(0, 1, Direction::Up)));
+        assert!(puzzle
+            .edge_stones
+            .contains(&EdgePos::new(1, 0, Direction::Right)));
+        assert!(puzzle.is_blocked(&EdgePos::new(1, 2, Direction::Right)));
+        assert!(puzzle.is_blocked(&EdgePos::new(3, 0, Direction::Up)));
+        assert_eq!(puzzle.squares[&Pos::new(0, 1)], 0);
+        assert_eq!(puzzle.stars[&Pos::new(1, 1)], 1);
+        assert_eq!(puzzle.cancels[&Pos::new(2, 1)], 1);
+        assert_eq!(puzzle.triangles[&Pos::new(0, 0)], 2);
+        assert_eq!(
+            puzzle.polys[&Pos::new(1, 0)],
+            Poly {
+                rotatable: true,
+                minos: vec![Pos::new(0, 0), Pos::new(0, 1), Pos::new(1, 0)],
+            }
+        );
+        assert_eq!(puzzle.ylops[&Pos::new(3, 0)].minos, vec![Pos::new(0, 0)]);
+        assert!(puzzle.outside_positions.contains(&Pos::new(2, 0)));
+
+        // Serializing gives back the same text
+        assert_eq!(render(&puzzle, None).unwrap(), src.trim_start());
+    }
+
+    #[test]
+    fn test_render_limits() {
+        // The last colour with a letter and the most numbered polyominoes
+        let mut puzzle = Puzzle::default_with_size(10, 10);
+        let block = Poly {
+            rotatable: false,
+            minos: vec![Pos::new(0, 0)],
+        };
+        for idx in 0..99 {
+            let pos = Pos::new(idx % 10, idx / 10);
+            puzzle.polys.insert(pos, block.clone());
+        }
+        puzzle.squares.insert(Pos::new(9, 9), 25);
+
+        let text = render(&puzzle, None).unwrap();
+        assert!(text.contains("|[z]|") && text.contains("|P99|"));
+        let parsed = parse(&text).unwrap();
+        assert_eq!(parsed.squares, puzzle.squares);
+        assert_eq!(parsed.polys, puzzle.polys);
+        assert_eq!(render(&parsed, None).unwrap(), text);
+
+        puzzle.squares.insert(Pos::new(9, 9), 26);
+        assert_eq!(
+            render(&puzzle, None).err(),
+            Some("colour 26 has no letter, only 26 colours can be drawn".into())
+        );
+
+        puzzle.squares.clear();
+        puzzle.polys.insert(Pos::new(9, 9), block);
+        assert_eq!(
+            render(&puzzle, None).err(),
+            Some("100 polyominoes can't be numbered, the most is 99".into())
+        );
+
+        puzzle.polys.clear();
+        puzzle.triangles.insert(Pos::new(9, 9), 5);
+        assert_eq!(
+            render(&puzzle, None).err(),
+            Some("cell (9, 9) has 5 triangles".into())
+        );
+        puzzle.triangles.insert(Pos::new(9, 9), 0);
+        assert_eq!(
+            render(&puzzle, None).err(),
+            Some("cell (9, 9) has 0 triangles".into())
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            parse("+---E\n|   |").err(),
+            Some("The grid needs an odd number of rows, and at least one cell".into())
+        );
+        assert_eq!(
+            parse("+---E\n| ? |\nS---+").err(),
+            Some("line 2: unexpected \" ? \" in a cell".into())
+        );
+        assert_eq!(
+            parse("+-=-E\n|   |\nS---+").err(),
+            Some("line 1: unexpected \"-=-\" on an edge".into())
+        );
+        assert_eq!(
+            parse("+---E\n|[A]|\nS---+").err(),
+            Some("line 2: invalid colour 'A'".into())
+        );
+        assert_eq!(
+            parse("+---E\n|P1 |\nS---+").err(),
+            Some("line 2: no shape for P1".into())
+        );
+        assert_eq!(
+            parse("+---+\n|   |\nS---+").err(),
+            Some("puzzle has no end".into())
+        );
+    }
+}
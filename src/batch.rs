@@ -0,0 +1,194 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use witness_solver::{
+    puzzle::Puzzle,
+    solver::{Limit, SearchOutcome},
+};
+
+use crate::{cli::BatchOptions, count_json, new_solver};
+
+/// Name of a puzzle in the results, and its source if it could be read
+type Entry = (String, Result<String, String>);
+
+#[derive(Default)]
+pub struct Summary {
+    solved: usize,
+    unsolvable: usize,
+    limit_reached: usize,
+    invalid: usize,
+    time: Duration,
+}
+
+/// Solves every puzzle of the batch and writes the results file
+pub fn run(options: BatchOptions) -> Result<Summary, String> {
+    let entries = read_entries(&options.input)?;
+    let file = File::create(&options.output)
+        .map_err(|err| format!("could not create {}: {err}", options.output.display()))?;
+    let mut results = BufWriter::new(file);
+    let write_error = |err| format!("could not write {}: {err}", options.output.display());
+
+    let mut config = options.config;
+    let mut summary = Summary::default();
+    for (name, src) in entries {
+        let solver = src
+            .and_then(|src| Puzzle::from_json(&src))
+            .and_then(|puzzle| new_solver(&puzzle));
+        let res = match solver {
+            Err(err) => {
+                println!("{name}: invalid puzzle: {err}");
+                summary.invalid += 1;
+                json::object! { puzzle: name, error: err }
+            }
+            Ok(mut solver) => {
+                solver.config = config;
+                let before = Instant::now();
+                let result = solver.count();
+                let time = before.elapsed();
+                // The config can't be cloned because of the progress callback
+                config = std::mem::take(&mut solver.config);
+
+                println!(
+                    "{name}: {} solutions, {} states visited in {time:?} ({})",
+                    result.count, solver.stats.states_visited, result.outcome
+                );
+                summary.time += time;
+                match result.outcome {
+                    SearchOutcome::Finished if result.count == 0 => summary.unsolvable += 1,
+                    SearchOutcome::Finished | SearchOutcome::LimitReached(Limit::Solutions) => {
+                        summary.solved += 1
+                    }
+                    _ => summary.limit_reached += 1,
+                }
+
+                json::object! {
+                    puzzle: name,
+                    outcome: result.outcome.to_string(),
+                    count: count_json(result.count),
+                    states_visited: solver.stats.states_visited,
+                    time: time.as_secs_f64(),
+                }
+            }
+        };
+        writeln!(results, "{}", res.dump()).map_err(write_error)?;
+    }
+    results.flush().map_err(write_error)?;
+
+    let total = summary.solved + summary.unsolvable + summary.limit_reached + summary.invalid;
+    println!();
+    println!("{total} puzzles in {:?}", summary.time);
+    println!("{:<16}{}", "Solved:", summary.solved);
+    println!("{:<16}{}", "Unsolvable:", summary.unsolvable);
+    println!("{:<16}{}", "Limit reached:", summary.limit_reached);
+    println!("{:<16}{}", "Invalid:", summary.invalid);
+    println!("Results written to {}", options.output.display());
+
+    Ok(summary)
+}
+
+/// Lists the puzzles of a directory of JSON files or of a JSONL file
+fn read_entries(input: &Path) -> Result<Vec<Entry>, String> {
+    let read_error = |err| format!("could not read {}: {err}", input.display());
+
+    if input.is_dir() {
+        let mut paths = Vec::new();
+        for entry in std::fs::read_dir(input).map_err(read_error)? {
+            let path = entry.map_err(read_error)?.path();
+            if path.extension().is_some_and(|ext| ext == "json") {
+                paths.push(path);
+            }
+        }
+        paths.sort();
+
+        Ok(paths
+            .into_iter()
+            .map(|path| {
+                let name = path.file_name().unwrap().to_string_lossy().into_owned();
+                let src = std::fs::read_to_string(&path).map_err(|err| err.to_string());
+                (name, src)
+            })
+            .collect())
+    } else {
+        let name = input.file_name().unwrap_or_default().to_string_lossy();
+        let src = std::fs::read_to_string(input).map_err(read_error)?;
+
+        Ok(src
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(idx, line)| (format!("{name}:{}", idx + 1), Ok(line.to_string())))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use witness_solver::solver::BFSSolverConfig;
+
+    use super::*;
+
+    /// Puzzle without symbols in the JSON format, from the bottom left to the top right
+    fn empty_puzzle(width: usize, height: usize) -> String {
+        let mut grid = json::JsonValue::new_array();
+        for x in 0..=width * 2 {
+            let mut column = json::JsonValue::new_array();
+            for y in 0..=height * 2 {
+                let mut cell = json::object! { type: "line" };
+                if x == 0 && y == height * 2 {
+                    cell["start"] = true.into();
+                }
+                if x == width * 2 && y == 0 {
+                    cell["end"] = "top".into();
+                }
+                column.push(cell).unwrap();
+            }
+            grid.push(column).unwrap();
+        }
+
+        json::object! { width: width * 2 + 1, height: height * 2 + 1, grid: grid }.dump()
+    }
+
+    #[test]
+    fn test_run() {
+        let dir = std::env::temp_dir().join(format!("witness-batch-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let input = dir.join("puzzles.jsonl");
+        let output = dir.join("results.jsonl");
+        let lines = [empty_puzzle(1, 1), "{}".to_string(), empty_puzzle(6, 6)];
+        std::fs::write(&input, lines.join("\n")).unwrap();
+
+        let summary = run(BatchOptions {
+            input,
+            output: output.clone(),
+            config: BFSSolverConfig {
+                max_states: 1000,
+                ..Default::default()
+            },
+        })
+        .unwrap();
+        let results = std::fs::read_to_string(&output).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(summary.solved, 1);
+        assert_eq!(summary.unsolvable, 0);
+        assert_eq!(summary.limit_reached, 1);
+        assert_eq!(summary.invalid, 1);
+
+        let results: Vec<_> = results
+            .lines()
+            .map(|line| json::parse(line).unwrap())
+            .collect();
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0]["puzzle"], "puzzles.jsonl:1");
+        assert_eq!(results[0]["outcome"], "finished");
+        assert_eq!(results[0]["count"], 2);
+        assert_eq!(results[1]["puzzle"], "puzzles.jsonl:2");
+        assert!(results[1]["error"].is_string());
+        assert_eq!(results[2]["puzzle"], "puzzles.jsonl:3");
+        assert_eq!(results[2]["outcome"], "state limit");
+    }
+}
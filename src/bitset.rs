@@ -0,0 +1,260 @@
+use std::marker::PhantomData;
+
+use crate::puzzle::*;
+
+/// Fixed-size set of integers in `0..capacity`, stored as a bitmask
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct BitSet {
+    words: Box<[u64]>,
+}
+
+impl BitSet {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            words: vec![0; capacity.div_ceil(64)].into(),
+        }
+    }
+
+    #[inline(always)]
+    pub fn contains(&self, idx: usize) -> bool {
+        self.words
+            .get(idx / 64)
+            .is_some_and(|word| word & (1 << (idx % 64)) != 0)
+    }
+
+    /// Adds the value to the set, returns true if it was not present before
+    #[inline(always)]
+    pub fn insert(&mut self, idx: usize) -> bool {
+        let word = &mut self.words[idx / 64];
+        let mask = 1 << (idx % 64);
+        let inserted = *word & mask == 0;
+        *word |= mask;
+        inserted
+    }
+
+    pub fn clear(&mut self) {
+        self.words.fill(0);
+    }
+
+    /// Number of bytes allocated for the set
+    pub fn heap_size(&self) -> usize {
+        std::mem::size_of_val(&*self.words)
+    }
+
+    /// Iterates over the values in the set, in increasing order
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        self.words.iter().enumerate().flat_map(|(word_idx, &word)| {
+            let mut word = word;
+            std::iter::from_fn(move || {
+                if word == 0 {
+                    return None;
+                }
+                let bit = word.trailing_zeros() as usize;
+                word &= word - 1;
+                Some(word_idx * 64 + bit)
+            })
+        })
+    }
+}
+
+/// Describes how the elements of a puzzle grid are numbered in a [`GridSet`]
+pub trait GridLayout {
+    type Item;
+
+    /// Number of elements in a grid of the given size
+    fn capacity(width: Coord, height: Coord) -> usize;
+    /// Index of the element, if it is part of the grid
+    fn index(item: &Self::Item, width: Coord, height: Coord) -> Option<usize>;
+    /// Element at the given index
+    fn item(idx: usize, width: Coord, height: Coord) -> Self::Item;
+}
+
+/// Vertices of the puzzle, from `(0, 0)` to `(width, height)`
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct Vertices;
+
+/// Cells of the puzzle, from `(0, 0)` to `(width - 1, height - 1)`.
+/// This includes cells in `outside_positions`.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct Cells;
+
+/// Edges going up or right from every vertex of the puzzle
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct Edges;
+
+impl GridLayout for Vertices {
+    type Item = Pos;
+
+    fn capacity(width: Coord, height: Coord) -> usize {
+        (width as usize + 1) * (height as usize + 1)
+    }
+
+    #[inline(always)]
+    fn index(pos: &Pos, width: Coord, height: Coord) -> Option<usize> {
+        if pos.x < 0 || pos.x > width || pos.y < 0 || pos.y > height {
+            return None;
+        }
+        Some(pos.x as usize * (height as usize + 1) + pos.y as usize)
+    }
+
+    fn item(idx: usize, _width: Coord, height: Coord) -> Pos {
+        let column = height as usize + 1;
+        Pos::new((idx / column) as Coord, (idx % column) as Coord)
+    }
+}
+
+impl GridLayout for Cells {
+    type Item = Pos;
+
+    fn capacity(width: Coord, height: Coord) -> usize {
+        width as usize * height as usize
+    }
+
+    #[inline(always)]
+    fn index(pos: &Pos, width: Coord, height: Coord) -> Option<usize> {
+        if pos.x < 0 || pos.x >= width || pos.y < 0 || pos.y >= height {
+            return None;
+        }
+        Some(pos.x as usize * height as usize + pos.y as usize)
+    }
+
+    fn item(idx: usize, _width: Coord, height: Coord) -> Pos {
+        Pos::new(
+            (idx / height as usize) as Coord,
+            (idx % height as usize) as Coord,
+        )
+    }
+}
+
+impl GridLayout for Edges {
+    type Item = EdgePos;
+
+    fn capacity(width: Coord, height: Coord) -> usize {
+        2 * Vertices::capacity(width, height)
+    }
+
+    #[inline(always)]
+    fn index(edge: &EdgePos, width: Coord, height: Coord) -> Option<usize> {
+        let edge = edge.normalize();
+        let vertex = Vertices::index(&edge.pos, width, height)?;
+        Some(2 * vertex + (edge.dir == Direction::Right) as usize)
+    }
+
+    fn item(idx: usize, width: Coord, height: Coord) -> EdgePos {
+        let pos = Vertices::item(idx / 2, width, height);
+        let dir = if idx.is_multiple_of(2) {
+            Direction::Up
+        } else {
+            Direction::Right
+        };
+        EdgePos { pos, dir }
+    }
+}
+
+/// Set of vertices, cells or edges of a puzzle, with constant time operations
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct GridSet<L: GridLayout> {
+    bits: BitSet,
+    width: Coord,
+    height: Coord,
+    layout: PhantomData<L>,
+}
+
+pub type VertexSet = GridSet<Vertices>;
+pub type CellSet = GridSet<Cells>;
+pub type EdgeSet = GridSet<Edges>;
+
+impl<L: GridLayout> GridSet<L> {
+    /// Creates an empty set sized for the given puzzle
+    pub fn new(puzzle: &Puzzle) -> Self {
+        Self {
+            bits: BitSet::new(L::capacity(puzzle.width, puzzle.height)),
+            width: puzzle.width,
+            height: puzzle.height,
+            layout: PhantomData,
+        }
+    }
+
+    #[inline(always)]
+    pub fn contains(&self, item: &L::Item) -> bool {
+        L::index(item, self.width, self.height).is_some_and(|idx| self.bits.contains(idx))
+    }
+
+    /// Adds the element to the set, returns true if it was not present before.
+    /// The element must be part of the grid.
+    #[inline(always)]
+    pub fn insert(&mut self, item: &L::Item) -> bool {
+        let idx = L::index(item, self.width, self.height);
+        debug_assert!(idx.is_some(), "inserting an element outside of the grid");
+        idx.is_some_and(|idx| self.bits.insert(idx))
+    }
+
+    pub fn clear(&mut self) {
+        self.bits.clear()
+    }
+
+    /// Number of bytes allocated for the set
+    pub fn heap_size(&self) -> usize {
+        self.bits.heap_size()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = L::Item> + '_ {
+        self.bits
+            .iter()
+            .map(|idx| L::item(idx, self.width, self.height))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bitset() {
+        let mut set = BitSet::new(130);
+        assert!(set.insert(0));
+        assert!(set.insert(64));
+        assert!(set.insert(129));
+        assert!(!set.insert(64));
+        assert!(set.contains(129));
+        assert!(!set.contains(1));
+        assert!(!set.contains(1000));
+        assert_eq!(set.iter().collect::<Vec<_>>(), vec![0, 64, 129]);
+        set.clear();
+        assert_eq!(set.iter().count(), 0);
+    }
+
+    #[test]
+    fn test_grid_sets() {
+        let puzzle = Puzzle::default_with_size(3, 2);
+
+        let mut vertices = VertexSet::new(&puzzle);
+        assert!(vertices.insert(&Pos::new(3, 2)));
+        assert!(!vertices.insert(&Pos::new(3, 2)));
+        assert!(!vertices.contains(&Pos::new(4, 0)));
+        assert!(!vertices.contains(&Pos::new(-1, 0)));
+        assert_eq!(vertices.iter().collect::<Vec<_>>(), vec![Pos::new(3, 2)]);
+
+        let mut cells = CellSet::new(&puzzle);
+        assert!(!cells.contains(&Pos::new(3, 2)));
+        assert!(cells.insert(&Pos::new(2, 1)));
+        assert!(cells.contains(&Pos::new(2, 1)));
+        assert_eq!(cells.iter().collect::<Vec<_>>(), vec![Pos::new(2, 1)]);
+
+        // Both directions of an edge are the same element
+        let mut edges = EdgeSet::new(&puzzle);
+        assert!(edges.insert(&EdgePos::new(1, 1, Direction::Down)));
+        assert!(edges.contains(&EdgePos::new(1, 0, Direction::Up)));
+        assert!(!edges.contains(&EdgePos::new(0, 0, Direction::Left)));
+        let items: Vec<_> = edges.iter().collect();
+        assert!(items[0].true_eq(&EdgePos::new(1, 0, Direction::Up)));
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "outside of the grid")]
+    fn test_insert_outside_grid() {
+        let puzzle = Puzzle::default_with_size(3, 2);
+        VertexSet::new(&puzzle).insert(&Pos::new(4, 0));
+    }
+}
@@ -0,0 +1,337 @@
+use std::{path::PathBuf, time::Duration};
+
+use witness_solver::solver::BFSSolverConfig;
+
+/// Time limit of each puzzle of a batch, so that one hard puzzle can't stall it
+const BATCH_TIME_LIMIT: Duration = Duration::from_secs(60);
+
+pub const USAGE: &str = "\
+Usage: witness-solver [OPTIONS] [FILE]
+       witness-solver batch [OPTIONS] <DIR|FILE.jsonl>
+       witness-solver play <FILE>
+
+Solves a puzzle read from FILE, or from the standard input if FILE is
+missing or `-`. The puzzle is either in the JSON format of witnesspuzzles.com,
+or in the text format printed by `--draw`.
+
+The batch command solves every `.json` file of DIR, or every line of a
+JSONL file, counting the solutions of each puzzle. It keeps going when
+a puzzle is invalid or a limit is reached, and writes one JSON line per
+puzzle to the results file.
+
+The play command lets you draw a path on the puzzle in the terminal.
+
+Output:
+  --format <FORMAT>         text (default), json, or count
+  --progress                Show the progress of the search on stderr
+  --draw                    Draw the puzzle and the solutions in text output
+  --svg <FILE>              Draw the puzzle and the solutions to an SVG file
+  --by-regions              Group the solutions that split the puzzle into the
+                            same areas, and only list the shortest of each group
+  -o, --output <FILE>       Results file of the batch command
+                            (default: results.jsonl)
+
+Search features (all enabled by default):
+  --no-end-reachability     Disable the simple end reachability check
+  --no-edge-stones          Disable the early edge stone check
+  --no-partial-areas        Disable the partial area check
+  --no-closed-areas         Disable the closed area check
+  --no-transpositions       Disable transposition merging when counting
+
+Limits:
+  --max-solutions <N>       Stop after finding N solutions
+  --time-limit <SECONDS>    Stop the search after this many seconds
+                            (default for each puzzle of a batch: 60)
+  --max-states <N>          Stop the search after visiting N states
+  --max-queue <N>           Stop the search when N states are queued
+  --max-memory <MIB>        Stop the search when it uses this much memory
+
+Exit status:
+  0  the puzzle has solutions
+  1  the puzzle has no solution
+  2  a limit was reached before the search could finish
+  3  the arguments or the puzzle are invalid
+";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    Text,
+    Json,
+    Count,
+}
+
+pub struct SolveOptions {
+    /// Puzzle file, `None` for the standard input
+    pub input: Option<PathBuf>,
+    pub format: Format,
+    pub progress: bool,
+    /// Draw the solutions instead of listing their moves
+    pub draw: bool,
+    /// File to draw the solutions to
+    pub svg: Option<PathBuf>,
+    /// Only list one solution per partition of the puzzle into areas
+    pub by_regions: bool,
+    pub config: BFSSolverConfig,
+}
+
+pub struct BatchOptions {
+    /// Directory of puzzle files, or JSONL file with one puzzle per line
+    pub input: PathBuf,
+    pub output: PathBuf,
+    pub config: BFSSolverConfig,
+}
+
+pub enum Command {
+    Help,
+    Solve(SolveOptions),
+    Batch(BatchOptions),
+    /// Draw a path on the puzzle of the file in the terminal
+    Play(PathBuf),
+}
+
+impl Command {
+    /// Parses the arguments of the program, without the program name
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut args = args.into_iter().peekable();
+        if args.peek().is_some_and(|arg| arg == "batch") {
+            args.next();
+            return Self::parse_batch(args);
+        }
+        if args.peek().is_some_and(|arg| arg == "play") {
+            args.next();
+            return Self::parse_play(args);
+        }
+
+        let mut options = SolveOptions {
+            input: None,
+            format: Format::Text,
+            progress: false,
+            draw: false,
+            svg: None,
+            by_regions: false,
+            config: Default::default(),
+        };
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "-h" | "--help" => return Ok(Command::Help),
+                "--format" => {
+                    options.format = match value(&arg, &mut args)?.as_str() {
+                        "text" => Format::Text,
+                        "json" => Format::Json,
+                        "count" => Format::Count,
+                        other => return Err(format!("unknown format {other:?}")),
+                    }
+                }
+                "--progress" => options.progress = true,
+                "--draw" => options.draw = true,
+                "--svg" => options.svg = Some(value(&arg, &mut args)?.into()),
+                "--by-regions" => options.by_regions = true,
+                "-" => options.input = None,
+                _ if parse_config_flag(&arg, &mut args, &mut options.config)? => {}
+                _ if arg.starts_with('-') => return Err(format!("unknown option {arg:?}")),
+                _ if options.input.is_some() => return Err(format!("unexpected argument {arg:?}")),
+                _ => options.input = Some(arg.into()),
+            }
+        }
+
+        Ok(Command::Solve(options))
+    }
+
+    fn parse_play(args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut input: Option<PathBuf> = None;
+        for arg in args {
+            match arg.as_str() {
+                "-h" | "--help" => return Ok(Command::Help),
+                _ if arg.starts_with('-') => return Err(format!("unknown option {arg:?}")),
+                _ if input.is_some() => return Err(format!("unexpected argument {arg:?}")),
+                _ => input = Some(arg.into()),
+            }
+        }
+
+        // The standard input is needed for the keyboard
+        Ok(Command::Play(input.ok_or("missing the puzzle file")?))
+    }
+
+    fn parse_batch(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut input: Option<PathBuf> = None;
+        let mut output = PathBuf::from("results.jsonl");
+        let mut config = BFSSolverConfig {
+            time_limit: Some(BATCH_TIME_LIMIT),
+            ..Default::default()
+        };
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "-h" | "--help" => return Ok(Command::Help),
+                "-o" | "--output" => output = value(&arg, &mut args)?.into(),
+                _ if parse_config_flag(&arg, &mut args, &mut config)? => {}
+                _ if arg.starts_with('-') => return Err(format!("unknown option {arg:?}")),
+                _ if input.is_some() => return Err(format!("unexpected argument {arg:?}")),
+                _ => input = Some(arg.into()),
+            }
+        }
+
+        Ok(Command::Batch(BatchOptions {
+            input: input.ok_or("missing the puzzles to solve")?,
+            output,
+            config,
+        }))
+    }
+}
+
+/// Applies the flag to the config if it is one of the search options.
+/// Returns false if the flag is not a search option.
+fn parse_config_flag(
+    flag: &str,
+    args: &mut impl Iterator<Item = String>,
+    config: &mut BFSSolverConfig,
+) -> Result<bool, String> {
+    match flag {
+        "--no-end-reachability" => config.simple_end_reachability_check = false,
+        "--no-edge-stones" => config.edge_stones = false,
+        "--no-partial-areas" => config.partial_area_check = false,
+        "--no-closed-areas" => config.closed_area_check = false,
+        "--no-transpositions" => config.merge_transpositions = false,
+        "--max-solutions" => config.max_solutions = number(flag, args)?,
+        "--time-limit" => {
+            let seconds: f64 = number(flag, args)?;
+            config.time_limit = Some(
+                Duration::try_from_secs_f64(seconds)
+                    .map_err(|_| format!("invalid time limit {seconds}"))?,
+            );
+        }
+        "--max-states" => config.max_states = number(flag, args)?,
+        "--max-queue" => config.max_queue_size = number(flag, args)?,
+        "--max-memory" => {
+            let mib: usize = number(flag, args)?;
+            config.max_memory = mib
+                .checked_mul(1024 * 1024)
+                .ok_or(format!("invalid value \"{mib}\" for {flag}"))?;
+        }
+        _ => return Ok(false),
+    }
+
+    Ok(true)
+}
+
+/// Returns the value that follows the flag
+fn value(flag: &str, args: &mut impl Iterator<Item = String>) -> Result<String, String> {
+    args.next().ok_or(format!("missing value for {flag}"))
+}
+
+/// Returns the numeric value that follows the flag
+fn number<T: std::str::FromStr>(
+    flag: &str,
+    args: &mut impl Iterator<Item = String>,
+) -> Result<T, String> {
+    let value = value(flag, args)?;
+    value
+        .parse()
+        .map_err(|_| format!("invalid value {value:?} for {flag}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &str) -> Result<Command, String> {
+        Command::parse(args.split_whitespace().map(String::from))
+    }
+
+    #[test]
+    fn test_parse_solve() {
+        let Ok(Command::Solve(options)) =
+            parse("--format count --no-edge-stones --max-solutions 3 --time-limit 1.5 puzzle.json")
+        else {
+            panic!("expected a solve command");
+        };
+
+        assert_eq!(options.input, Some("puzzle.json".into()));
+        assert_eq!(options.format, Format::Count);
+        assert!(!options.progress);
+        assert!(!options.draw);
+        assert_eq!(options.svg, None);
+        assert!(!options.by_regions);
+        assert!(!options.config.edge_stones);
+        assert!(options.config.partial_area_check);
+        assert_eq!(options.config.max_solutions, 3);
+        assert_eq!(options.config.time_limit, Some(Duration::from_millis(1500)));
+
+        let Ok(Command::Solve(options)) = parse("- --by-regions") else {
+            panic!("expected a solve command");
+        };
+        assert_eq!(options.input, None);
+        assert_eq!(options.format, Format::Text);
+        assert!(options.by_regions);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(matches!(parse("--help"), Ok(Command::Help)));
+        assert_eq!(
+            parse("--format yaml").err(),
+            Some("unknown format \"yaml\"".into())
+        );
+        assert_eq!(
+            parse("--max-states").err(),
+            Some("missing value for --max-states".into())
+        );
+        assert_eq!(
+            parse("--max-states many").err(),
+            Some("invalid value \"many\" for --max-states".into())
+        );
+        assert_eq!(
+            parse(&format!("--max-memory {}", usize::MAX)).err(),
+            Some(format!("invalid value \"{}\" for --max-memory", usize::MAX))
+        );
+        assert_eq!(
+            parse("--fast").err(),
+            Some("unknown option \"--fast\"".into())
+        );
+        assert_eq!(
+            parse("a.json b.json").err(),
+            Some("unexpected argument \"b.json\"".into())
+        );
+    }
+
+    #[test]
+    fn test_parse_batch() {
+        let Ok(Command::Batch(options)) = parse("batch puzzles -o out.jsonl --time-limit 10")
+        else {
+            panic!("expected a batch command");
+        };
+        assert_eq!(options.input, PathBuf::from("puzzles"));
+        assert_eq!(options.output, PathBuf::from("out.jsonl"));
+        assert_eq!(options.config.time_limit, Some(Duration::from_secs(10)));
+
+        let Ok(Command::Batch(options)) = parse("batch puzzles.jsonl") else {
+            panic!("expected a batch command");
+        };
+        assert_eq!(options.output, PathBuf::from("results.jsonl"));
+        assert_eq!(options.config.time_limit, Some(BATCH_TIME_LIMIT));
+
+        assert_eq!(
+            parse("batch").err(),
+            Some("missing the puzzles to solve".into())
+        );
+
+        assert_eq!(
+            parse("batch puzzles --format json").err(),
+            Some("unknown option \"--format\"".into())
+        );
+    }
+
+    #[test]
+    fn test_parse_play() {
+        let Ok(Command::Play(input)) = parse("play p.txt") else {
+            panic!("expected a play command");
+        };
+        assert_eq!(input, PathBuf::from("p.txt"));
+        assert_eq!(parse("play").err(), Some("missing the puzzle file".into()));
+        assert_eq!(
+            parse("play a.txt b.txt").err(),
+            Some("unexpected argument \"b.txt\"".into())
+        );
+    }
+}
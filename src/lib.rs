@@ -0,0 +1,6 @@
+pub mod ascii;
+pub mod bitset;
+pub mod puzzle;
+pub mod regions;
+pub mod solver;
+pub mod svg;
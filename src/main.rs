@@ -1,44 +1,233 @@
-use std::time::Instant;
+use std::{io::Read, path::PathBuf, process::ExitCode, time::Instant};
 
-use crate::{puzzle::*, solver::BFSSolverConfig};
+use cli::{Command, Format, SolveOptions, USAGE};
+use json::JsonValue;
+use witness_solver::{
+    ascii,
+    puzzle::*,
+    solver::{BFSSolver, Limit, Progress, SearchOutcome, SearchResult, SolverStats},
+    svg::{self, SvgConfig},
+};
 
-mod puzzle;
-mod solver;
+mod batch;
+mod cli;
+mod play;
 
-fn main() {
-    let puzzle = Puzzle::from_json(r#"{"grid":[[{"type":"line","line":0,"dir":null},{"type":"line","line":0,"dir":null},{"type":"line","line":0,"dir":null},{"type":"line","line":0,"dir":null,"gap":2,"dot":null,"start":null,"end":null},{"type":"line","line":0,"dir":null,"start":false,"end":null},{"type":"line","line":0,"dir":null,"gap":2,"dot":null,"start":null,"end":null},{"type":"line","line":1,"dir":"right"},{"type":"line","line":1,"dir":"top"},{"type":"line","line":1,"dir":"top"},{"type":"line","line":0,"dir":null},{"type":"line","line":1,"dir":"right"},{"type":"line","line":1,"dir":"top"},{"type":"line","line":1,"dir":"top"},{"type":"line","line":0,"dir":null},{"type":"line","line":0,"dir":null},{"type":"line","line":0,"dir":null},{"type":"line","line":1,"start":true,"dir":"right"}],[{"type":"line","line":0,"dir":null},{"type":"square","color":"red","line":0,"dir":null},{"type":"line","line":0,"dir":null},null,{"type":"line","line":0,"dir":null,"gap":2,"dot":null,"start":null,"end":null},null,{"type":"line","line":1,"dir":"right"},{"type":"square","color":"white","line":0,"dir":null},{"type":"line","line":1,"dir":"left"},{"type":"square","color":"purple","line":0,"dir":null},{"type":"line","line":1,"dir":"right"},{"type":"square","color":"purple","line":0,"dir":null},{"type":"line","line":1,"dir":"left"},{"type":"square","color":"purple","line":0,"dir":null},{"type":"line","line":0,"dir":null},{"type":"square","color":"purple","line":0,"dir":null},{"type":"line","line":1,"dir":"right"}],[{"type":"line","line":1,"dir":"right"},{"type":"line","line":1,"dir":"top"},{"type":"line","line":1,"dir":"top"},{"type":"line","line":0,"dir":null},{"type":"line","line":1,"dir":"right"},{"type":"line","line":1,"dir":"top"},{"type":"line","line":1,"dir":"top"},{"type":"line","line":0,"dir":null},{"type":"line","line":1,"dir":"left"},{"type":"line","line":0,"dir":null},{"type":"line","line":1,"dir":"right"},{"type":"line","line":0,"dir":null},{"type":"line","line":1,"dir":"left"},{"type":"line","line":0,"dir":null},{"type":"line","line":1,"dir":"right"},{"type":"line","line":1,"dir":"top"},{"type":"line","line":1,"dir":"top"}],[{"type":"line","line":1,"dir":"right"},{"type":"square","color":"white","line":0,"dir":null},{"type":"line","line":1,"dir":"left"},{"type":"square","color":"red","line":0,"dir":null},{"type":"line","line":1,"dir":"right"},{"type":"square","color":"white","line":0,"dir":null},{"type":"line","line":0,"dir":null},{"type":"square","color":"white","line":0,"dir":null},{"type":"line","line":1,"dir":"left"},{"type":"square","color":"purple","line":0,"dir":null},{"type":"line","line":1,"dir":"right"},{"type":"square","color":"purple","line":0,"dir":null},{"type":"line","line":1,"dir":"left"},{"type":"square","color":"purple","line":0,"dir":null},{"type":"line","line":1,"dir":"right"},{"type":"square","color":"red","line":0,"dir":null},{"type":"line","line":0,"dir":null}],[{"type":"line","line":1,"dir":null,"end":"top"},{"type":"line","line":0,"dir":null},{"type":"line","line":1,"dir":"left"},{"type":"line","line":1,"dir":"top"},{"type":"line","line":1,"dir":"top"},{"type":"line","line":0,"dir":null},{"type":"line","line":0,"dir":null},{"type":"line","line":0,"dir":null},{"type":"line","line":1,"dir":"left"},{"type":"line","line":0,"dir":null},{"type":"line","line":1,"dir":"right"},{"type":"line","line":0,"dir":null},{"type":"line","line":1,"dir":"left"},{"type":"line","line":0,"dir":null},{"type":"line","line":1,"dir":"right"},{"type":"line","line":0,"dir":null},{"type":"line","line":0,"dir":null}],[{"type":"line","line":0,"dir":null,"gap":2,"dot":null,"start":null,"end":null},null,{"type":"line","line":0,"dir":null,"gap":2,"dot":null,"start":null,"end":null},null,{"type":"line","line":0,"dir":null},{"type":"square","color":"white","line":0,"dir":null},{"type":"line","line":0,"dir":null},{"type":"square","color":"white","line":0,"dir":null},{"type":"line","line":1,"dir":"left"},{"type":"square","color":"purple","line":0,"dir":null},{"type":"line","line":1,"dir":"right"},{"type":"square","color":"purple","line":0,"dir":null},{"type":"line","line":1,"dir":"left"},{"type":"square","color":"purple","line":0,"dir":null},{"type":"line","line":1,"dir":"right"},{"type":"square","color":"red","line":0,"dir":null},{"type":"line","line":0,"dir":null}],[{"type":"line","line":0,"dir":null,"start":false,"end":null},{"type":"line","line":0,"dir":null,"gap":2,"dot":null,"start":null,"end":null},{"type":"line","line":0,"dir":null,"start":false,"end":null},{"type":"line","line":0,"dir":null,"gap":2,"dot":null,"start":null,"end":null},{"type":"line","line":0,"dir":null},{"type":"line","line":0,"dir":null},{"type":"line","line":0,"dir":null},{"type":"line","line":0,"dir":null},{"type":"line","line":1,"dir":"left"},{"type":"line","line":0,"dir":null},{"type":"line","line":1,"dir":"right"},{"type":"line","line":0,"dir":null},{"type":"line","line":1,"dir":"left"},{"type":"line","line":0,"dir":null},{"type":"line","line":1,"dir":"down"},{"type":"line","line":1,"dir":"down"},{"type":"line","line":1,"dir":"right"}],[{"type":"line","line":0,"dir":null,"gap":2,"dot":null,"start":null,"end":null},null,{"type":"line","line":0,"dir":null,"gap":2,"dot":null,"start":null,"end":null},null,{"type":"line","line":0,"dir":null,"gap":2,"dot":null,"start":null,"end":null},null,{"type":"line","line":0,"dir":null},{"type":"square","color":"white","line":0,"dir":null},{"type":"line","line":1,"dir":"left"},{"type":"square","color":"purple","line":0,"dir":null},{"type":"line","line":1,"dir":"right"},{"type":"square","color":"purple","line":0,"dir":null},{"type":"line","line":1,"dir":"left"},{"type":"square","color":"purple","line":0,"dir":null},{"type":"line","line":0,"dir":null},{"type":"square","color":"purple","line":0,"dir":null},{"type":"line","line":1,"dir":"right"}],[{"type":"line","line":0,"dir":null,"start":false,"end":null},{"type":"line","line":0,"dir":null,"gap":2,"dot":null,"start":null,"end":null},{"type":"line","line":0,"dir":null,"start":false,"end":null},{"type":"line","line":0,"dir":null,"gap":2,"dot":null,"start":null,"end":null},{"type":"line","line":0,"dir":null,"start":false,"end":null},{"type":"line","line":0,"dir":null,"gap":2,"dot":null,"start":null,"end":null},{"type":"line","line":0,"dir":null},{"type":"line","line":0,"dir":null},{"type":"line","line":1,"dir":"left"},{"type":"line","line":1,"dir":"top"},{"type":"line","line":1,"dir":"top"},{"type":"line","line":0,"dir":null},{"type":"line","line":1,"dir":"left"},{"type":"line","line":1,"dir":"top"},{"type":"line","line":1,"dir":"top"},{"type":"line","line":1,"dir":"top"},{"type":"line","line":1,"dir":"top"}]],"largezero":306,"width":9,"height":17,"pillar":false,"settings":{"NEGATIONS_CANCEL_NEGATIONS":true,"SHAPELESS_ZERO_POLY":false,"PRECISE_POLYOMINOS":true,"FLASH_FOR_ERRORS":true,"FAT_STARTPOINTS":false,"CUSTOM_MECHANICS":false},"name":"Unnamed Puzzle","hasNegations":false,"hasPolyominos":false,"startPoint":{"x":0,"y":16},"endPoint":{"x":4,"y":0},"autoSolved":true,"path":[{"x":0,"y":16},2,2,3,3,2,2,2,2,4,4,2,2,3,3,3,3,1,1,1,1,1,1,1,1,3,3,2,2,2,2,2,2,2,2,3,3,1,1,1,1,1,1,1,1,3,3,2,2,3,3,2,2,3,3,1,1,3,3,2,2,0]}"#).unwrap();
+// Exit codes
+const SOLVED: u8 = 0;
+const UNSOLVABLE: u8 = 1;
+const LIMIT_REACHED: u8 = 2;
+const INVALID_INPUT: u8 = 3;
 
-    let bfssolver_config = BFSSolverConfig {
-        simple_end_reachability_check: true,
-        edge_stones: true,
-        partial_area_check: true,
-        closed_area_check: false,
-        max_solutions: 0,
+fn main() -> ExitCode {
+    let command = match Command::parse(std::env::args().skip(1)) {
+        Ok(command) => command,
+        Err(err) => {
+            eprintln!("error: {err}\n\n{USAGE}");
+            return ExitCode::from(INVALID_INPUT);
+        }
     };
 
-    let mut solver = solver::BFSSolver::new(&puzzle);
-    solver.config = bfssolver_config;
+    match command {
+        Command::Help => {
+            print!("{USAGE}");
+            ExitCode::SUCCESS
+        }
+        Command::Solve(options) => match solve(options) {
+            Ok(code) => ExitCode::from(code),
+            Err(err) => {
+                eprintln!("error: {err}");
+                ExitCode::from(INVALID_INPUT)
+            }
+        },
+        Command::Play(input) => match play::run(&input) {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(err) => {
+                eprintln!("error: {err}");
+                ExitCode::from(INVALID_INPUT)
+            }
+        },
+        Command::Batch(options) => match batch::run(options) {
+            Ok(_) => ExitCode::SUCCESS,
+            Err(err) => {
+                eprintln!("error: {err}");
+                ExitCode::from(INVALID_INPUT)
+            }
+        },
+    }
+}
+
+/// Reads and parses the puzzle from the file, or from stdin if there is none.
+/// The puzzle can be in JSON or in the text format of the `ascii` module.
+fn read_puzzle(input: &Option<PathBuf>) -> Result<Puzzle, String> {
+    let src = match input {
+        Some(path) => std::fs::read_to_string(path)
+            .map_err(|err| format!("could not read {}: {err}", path.display()))?,
+        None => {
+            let mut src = String::new();
+            std::io::stdin()
+                .read_to_string(&mut src)
+                .map_err(|err| format!("could not read the standard input: {err}"))?;
+            src
+        }
+    };
+
+    // Text puzzles start with the top row of the grid, never with a brace
+    if src.trim_start().starts_with('{') {
+        Puzzle::from_json(&src)
+    } else {
+        src.parse()
+    }
+}
+
+/// Solves the puzzle, prints the results and returns the exit code
+fn solve(options: SolveOptions) -> Result<u8, String> {
+    let puzzle = read_puzzle(&options.input)?;
+    // Fail before searching if the puzzle can't be drawn
+    let drawing = options
+        .draw
+        .then(|| ascii::render(&puzzle, None))
+        .transpose()?;
+    let mut solver = new_solver(&puzzle)?;
+    solver.config = options.config;
+    if options.progress {
+        solver.config.progress = Some(Box::new(print_progress));
+    }
 
     let before = Instant::now();
-    let solutions = solver.solve();
+    let SearchResult {
+        solutions,
+        count,
+        outcome,
+    } = match options.format {
+        Format::Count if !options.by_regions => solver.count(),
+        Format::Count | Format::Text | Format::Json => solver.solve(),
+    };
     let solve_time = before.elapsed();
+    if options.progress {
+        // End the progress line
+        eprintln!();
+    }
 
-    println!(
-        "Found {} solutions and visited {} states in {:?}",
-        solutions.len(),
-        solver.states_visited,
-        solve_time,
-    );
+    let classes = options
+        .by_regions
+        .then(|| puzzle.group_by_regions(&solutions))
+        .transpose()
+        .map_err(|err| err.to_string())?;
+    // Only one solution of each class is listed
+    let listed: Vec<&SolutionPath> = match &classes {
+        Some(classes) => classes.iter().map(|class| &class.representative).collect(),
+        None => solutions.iter().collect(),
+    };
 
-    if !solutions.is_empty() {
-        println!(
-            "Shortest solution ({} moves): {}",
-            solutions[0].len() - 1,
-            solutions[0]
-        );
+    match options.format {
+        Format::Count if options.by_regions => println!("{}", listed.len()),
+        Format::Count => println!("{count}"),
+        Format::Text => {
+            if outcome != SearchOutcome::Finished {
+                println!("The search was stopped early: {outcome}");
+            }
+            println!("Found {count} solutions in {solve_time:?}");
+            if let Some(classes) = &classes {
+                println!("{} distinct partitions into areas", classes.len());
+            }
+            println!("{}", solver.stats);
+            if let Some(drawing) = &drawing {
+                println!("{drawing}");
+            }
+            for (idx, solution) in listed.iter().enumerate() {
+                print!("{} moves: {}", solution.len() - 1, solution);
+                match &classes {
+                    Some(classes) => println!(" ({} solutions)", classes[idx].size),
+                    None => println!(),
+                }
+                if options.draw {
+                    println!("{}", ascii::render(&puzzle, Some(solution))?);
+                }
+            }
+        }
+        Format::Json => {
+            let mut res = json::object! {
+                outcome: outcome.to_string(),
+                count: count_json(count),
+                time: solve_time.as_secs_f64(),
+                stats: stats_json(&solver.stats),
+                solutions: solutions.iter().map(ToString::to_string).collect::<Vec<_>>(),
+            };
+            if let Some(classes) = &classes {
+                res["regions"] = classes
+                    .iter()
+                    .map(|class| {
+                        json::object! {
+                            solution: class.representative.to_string(),
+                            size: class.size,
+                        }
+                    })
+                    .collect::<Vec<_>>()
+                    .into();
+            }
+            println!("{}", res.pretty(2));
+        }
     }
 
-    for solution in &solutions[..solutions.len().min(10)] {
-        println!("{} moves: {}", solution.len() - 1, solution);
+    if let Some(file) = &options.svg {
+        let drawn: Vec<_> = listed.into_iter().cloned().collect();
+        let svg = svg::render_grid(&puzzle, &drawn, &SvgConfig::default());
+        std::fs::write(file, svg)
+            .map_err(|err| format!("could not write {}: {err}", file.display()))?;
+    }
+
+    Ok(exit_code(outcome, count))
+}
+
+fn new_solver(puzzle: &Puzzle) -> Result<BFSSolver, String> {
+    BFSSolver::new(puzzle).map_err(|errors| {
+        errors
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(", ")
+    })
+}
+
+fn exit_code(outcome: SearchOutcome, count: u128) -> u8 {
+    match outcome {
+        SearchOutcome::Finished | SearchOutcome::LimitReached(Limit::Solutions) if count > 0 => {
+            SOLVED
+        }
+        SearchOutcome::Finished => UNSOLVABLE,
+        _ => LIMIT_REACHED,
+    }
+}
+
+fn print_progress(progress: &Progress) {
+    eprint!(
+        "\r[{:.1?}] {} states visited, {} queued, {} moves, {} solutions, {} pruned",
+        progress.elapsed,
+        progress.stats.states_visited,
+        progress.queue_size,
+        progress.path_len,
+        progress.solutions,
+        progress.stats.pruned(),
+    );
+}
+
+/// Solution counts can go over what JSON numbers can hold, write them as strings then
+fn count_json(count: u128) -> JsonValue {
+    u64::try_from(count).map_or_else(|_| count.to_string().into(), Into::into)
+}
+
+fn stats_json(stats: &SolverStats) -> JsonValue {
+    json::object! {
+        states_visited: stats.states_visited,
+        states_merged: stats.states_merged,
+        pruned_edge_stones: stats.pruned_edge_stones,
+        pruned_partial_areas: stats.pruned_partial_areas,
+        pruned_end_reachability: stats.pruned_end_reachability,
+        pruned_closed_areas: stats.pruned_closed_areas,
+        full_validations: stats.full_validations,
+        validation_time: stats.validation_time.as_secs_f64(),
+        peak_queue_size: stats.peak_queue_size,
+        peak_memory: stats.peak_memory,
     }
 }
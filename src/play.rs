@@ -0,0 +1,328 @@
+use std::{
+    fs::File,
+    io::{Read, Write},
+    path::Path,
+    process::Stdio,
+    time::Duration,
+};
+
+use witness_solver::{ascii, puzzle::*, solver::BFSSolver};
+
+use crate::{new_solver, read_puzzle};
+
+/// How long a hint may search for solutions
+const HINT_TIME_LIMIT: Duration = Duration::from_secs(10);
+
+const HELP: &str = "Arrows or WASD: move   Backspace: undo   Tab: next start   \
+                    H: hint   R: restart   Q: quit";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Key {
+    Move(Direction),
+    Undo,
+    NextStart,
+    Hint,
+    Restart,
+    Quit,
+}
+
+impl Key {
+    /// Reads the next key that means something, or `None` at the end of the input
+    fn read(input: &mut impl Iterator<Item = u8>) -> Option<Self> {
+        loop {
+            let key = match input.next()? {
+                // Escape sequences of the arrow keys
+                0x1b => {
+                    if input.next()? != b'[' {
+                        continue;
+                    }
+                    match input.next()? {
+                        b'A' => Key::Move(Direction::Up),
+                        b'B' => Key::Move(Direction::Down),
+                        b'C' => Key::Move(Direction::Right),
+                        b'D' => Key::Move(Direction::Left),
+                        _ => continue,
+                    }
+                }
+                b'w' | b'W' => Key::Move(Direction::Up),
+                b's' | b'S' => Key::Move(Direction::Down),
+                b'd' | b'D' => Key::Move(Direction::Right),
+                b'a' | b'A' => Key::Move(Direction::Left),
+                0x7f | 0x08 | b'u' | b'U' => Key::Undo,
+                b'\t' => Key::NextStart,
+                b'h' | b'H' => Key::Hint,
+                b'r' | b'R' => Key::Restart,
+                // Ctrl-C and Ctrl-D too, as the terminal is in raw mode
+                b'q' | b'Q' | 0x03 | 0x04 => Key::Quit,
+                _ => continue,
+            };
+            return Some(key);
+        }
+    }
+}
+
+/// State of a game: the path drawn so far, and what to tell the player
+pub struct Game {
+    puzzle: Puzzle,
+    /// Index of the start the path begins at
+    start: usize,
+    path: SolutionPath,
+    message: String,
+    /// Solver of the puzzle, for hints
+    solver: BFSSolver,
+}
+
+impl Game {
+    pub fn new(puzzle: Puzzle) -> Self {
+        let path = vec![puzzle.starts[0]].into();
+        let mut solver = new_solver(&puzzle).unwrap();
+        solver.config.time_limit = Some(HINT_TIME_LIMIT);
+        Self {
+            puzzle,
+            start: 0,
+            path,
+            message: String::new(),
+            solver,
+        }
+    }
+
+    /// Applies the key, returns false when the game is over
+    pub fn handle(&mut self, key: Key) -> bool {
+        self.message.clear();
+
+        match key {
+            Key::Move(dir) => self.step(dir),
+            Key::Undo if self.path.len() > 1 => _ = self.path.pop(),
+            Key::Undo => {}
+            Key::NextStart => {
+                self.start = (self.start + 1) % self.puzzle.starts.len();
+                self.path = vec![self.puzzle.starts[self.start]].into();
+            }
+            Key::Hint => self.hint(),
+            Key::Restart => self.path.truncate(1),
+            Key::Quit => return false,
+        }
+
+        true
+    }
+
+    fn step(&mut self, dir: Direction) {
+        let head = *self.path.last().unwrap();
+        let next = head.move_direction(dir);
+
+        // Going back over the last edge erases it
+        if self.path.len() > 1 && self.path[self.path.len() - 2] == next {
+            self.path.pop();
+            return;
+        }
+
+        if !self.puzzle.contains_vertex(&next) {
+            self.message = "The path can't leave the puzzle".to_string();
+        } else if self.puzzle.is_blocked(&EdgePos::new(head.x, head.y, dir)) {
+            self.message = "This edge is broken".to_string();
+        } else if self.path.contains(&next) {
+            self.message = "The path can't cross itself".to_string();
+        } else {
+            self.path.push(next);
+            if self.puzzle.ends.contains(&next) {
+                self.check();
+            }
+        }
+    }
+
+    /// Tells whether the path is a solution, and what is wrong with it if not
+    fn check(&mut self) {
+        if self.puzzle.is_solution(&self.path) {
+            self.message = "Solved!".to_string();
+            return;
+        }
+
+        let mut problems = vec![];
+        if let Err(violations) = self.puzzle.validate(&self.path) {
+            for violation in violations {
+                problems.push(match violation.kind {
+                    ViolationKind::Path(err) => err.to_string(),
+                    ViolationKind::Cell(pos, _) => format!("cell {pos}"),
+                    ViolationKind::VertexStone(pos) => format!("stone {pos}"),
+                    ViolationKind::EdgeStone(edge) => format!("stone {}", edge.pos),
+                });
+            }
+        }
+        self.message = format!("Not a solution: {}", problems.join(", "));
+    }
+
+    /// Tells which moves from the head of the path can lead to a solution
+    fn hint(&mut self) {
+        let hint = self.solver.hint(&self.path).unwrap();
+        let moves: Vec<_> = hint.moves.iter().map(|&dir| direction_name(dir)).collect();
+
+        self.message = if !moves.is_empty() {
+            format!("Hint: go {}", moves.join(" or "))
+        } else if hint.complete {
+            "This is already a solution".to_string()
+        } else if !hint.is_dead_end() {
+            "No hint found before the time limit".to_string()
+        } else {
+            "This path can't be finished, go back a few moves".to_string()
+        };
+    }
+
+    /// Text of the whole screen
+    pub fn screen(&self) -> String {
+        format!(
+            "{}\n{}\nStart {} of {}: {}\n{}\n",
+            ascii::render(&self.puzzle, Some(&self.path)).unwrap(),
+            HELP,
+            self.start + 1,
+            self.puzzle.starts.len(),
+            self.path,
+            self.message,
+        )
+    }
+}
+
+fn direction_name(dir: Direction) -> &'static str {
+    match dir {
+        Direction::Up => "up",
+        Direction::Down => "down",
+        Direction::Right => "right",
+        Direction::Left => "left",
+    }
+}
+
+/// Puts the terminal in raw mode, and restores its settings when dropped
+struct RawMode {
+    saved: String,
+}
+
+impl RawMode {
+    fn enable() -> Result<Self, String> {
+        let saved = stty(&["-g"])?;
+        stty(&["raw", "-echo"])?;
+        Ok(Self {
+            saved: saved.trim().to_string(),
+        })
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        _ = stty(&[&self.saved]);
+    }
+}
+
+fn stty(args: &[&str]) -> Result<String, String> {
+    let tty = File::open("/dev/tty").map_err(|err| format!("no terminal available: {err}"))?;
+    let output = std::process::Command::new("stty")
+        .args(args)
+        .stdin(tty)
+        .stderr(Stdio::inherit())
+        .output()
+        .map_err(|err| format!("could not run stty: {err}"))?;
+    if !output.status.success() {
+        return Err("stty could not configure the terminal".to_string());
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Plays the puzzle in the terminal until the player quits
+pub fn run(input: &Path) -> Result<(), String> {
+    let puzzle = read_puzzle(&Some(input.to_path_buf()))?;
+    new_solver(&puzzle)?;
+    ascii::render(&puzzle, None)?;
+
+    let mut game = Game::new(puzzle);
+    let _raw_mode = RawMode::enable()?;
+    let mut keys = std::io::stdin().lock().bytes().map_while(Result::ok);
+    let mut stdout = std::io::stdout();
+
+    loop {
+        // Raw mode doesn't turn line feeds into new lines
+        let screen = game.screen().replace('\n', "\r\n");
+        write!(stdout, "\x1b[2J\x1b[H{screen}").map_err(|err| err.to_string())?;
+        stdout.flush().map_err(|err| err.to_string())?;
+
+        match Key::read(&mut keys) {
+            Some(key) if game.handle(key) => {}
+            _ => return Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn game() -> Game {
+        let puzzle: Puzzle = r"
++---E---+
+|[a]|[b]|
+S---X---+
+"
+        .parse()
+        .unwrap();
+        Game::new(puzzle)
+    }
+
+    #[test]
+    fn test_read_keys() {
+        let mut input = b"\x1b[Ax\x1b[D\x7f\th".iter().copied();
+        assert_eq!(Key::read(&mut input), Some(Key::Move(Direction::Up)));
+        assert_eq!(Key::read(&mut input), Some(Key::Move(Direction::Left)));
+        assert_eq!(Key::read(&mut input), Some(Key::Undo));
+        assert_eq!(Key::read(&mut input), Some(Key::NextStart));
+        assert_eq!(Key::read(&mut input), Some(Key::Hint));
+        assert_eq!(Key::read(&mut input), None);
+    }
+
+    #[test]
+    fn test_moves() {
+        let mut game = game();
+        game.handle(Key::Move(Direction::Left));
+        assert_eq!(game.message, "The path can't leave the puzzle");
+
+        game.handle(Key::Move(Direction::Up));
+        game.handle(Key::Move(Direction::Right));
+        assert_eq!(game.path.to_string(), "(0, 0) UR");
+        assert_eq!(game.message, "Not a solution: cell (1, 0)");
+        game.handle(Key::Move(Direction::Down));
+        assert_eq!(game.message, "Solved!");
+
+        game.handle(Key::Move(Direction::Left));
+        assert_eq!(game.message, "The path can't cross itself");
+        game.handle(Key::Move(Direction::Up));
+        assert_eq!(game.path.to_string(), "(0, 0) UR");
+
+        game.handle(Key::NextStart);
+        game.handle(Key::Move(Direction::Up));
+        assert_eq!(game.path.to_string(), "(1, 0) U");
+        assert_eq!(game.message, "Solved!");
+
+        assert!(!game.handle(Key::Quit));
+    }
+
+    #[test]
+    fn test_hint() {
+        let mut game = game();
+        game.handle(Key::Hint);
+        assert_eq!(game.message, "Hint: go up or right");
+        game.handle(Key::Move(Direction::Up));
+        game.handle(Key::Hint);
+        assert_eq!(game.message, "Hint: go right");
+
+        game.handle(Key::Move(Direction::Right));
+        game.handle(Key::Move(Direction::Right));
+        game.handle(Key::Hint);
+        assert_eq!(
+            game.message,
+            "This path can't be finished, go back a few moves"
+        );
+
+        game.handle(Key::Restart);
+        game.handle(Key::Move(Direction::Right));
+        game.handle(Key::Move(Direction::Up));
+        game.handle(Key::Hint);
+        assert_eq!(game.message, "This is already a solution");
+    }
+}
@@ -1,16 +1,25 @@
 #![allow(unused)]
 
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{hash_map::Entry, HashMap, HashSet},
     fmt::Display,
     hash::Hash,
     ops::{Add, Deref, DerefMut, Sub},
+    str::FromStr,
 };
 
+use crate::bitset::{CellSet, EdgeSet, VertexSet};
+
+/// Integer type used for coordinates.
+///
+/// Puzzles are limited to [`Puzzle::MAX_SIZE`] so that moving around,
+/// even slightly outside of the grid, never overflows.
+pub type Coord = i16;
+
 #[derive(Clone, Copy, PartialEq, Eq, Debug, Hash)]
 pub struct Pos {
-    pub x: i8,
-    pub y: i8,
+    pub x: Coord,
+    pub y: Coord,
 }
 
 impl Display for Pos {
@@ -42,22 +51,35 @@ impl Sub for Pos {
 }
 
 impl Pos {
-    pub const fn new(x: i8, y: i8) -> Self {
+    pub const fn new(x: Coord, y: Coord) -> Self {
         Self { x, y }
     }
 
+    /// Returns the neighbour in the given direction. This can't overflow for
+    /// positions of a puzzle, use [`Pos::checked_move_direction`] for others.
     #[inline(always)]
     pub fn move_direction(&self, dir: Direction) -> Pos {
         let mut res = *self;
         match dir {
-            Direction::Up => res.y = res.y.wrapping_add(1),
-            Direction::Down => res.y = res.y.wrapping_sub(1),
-            Direction::Right => res.x = res.x.wrapping_add(1),
-            Direction::Left => res.x = res.x.wrapping_sub(1),
+            Direction::Up => res.y += 1,
+            Direction::Down => res.y -= 1,
+            Direction::Right => res.x += 1,
+            Direction::Left => res.x -= 1,
         }
         res
     }
 
+    /// Same as [`Pos::move_direction`], or `None` if the neighbour's coordinates overflow
+    pub fn checked_move_direction(&self, dir: Direction) -> Option<Pos> {
+        let (x, y) = match dir {
+            Direction::Up => (Some(self.x), self.y.checked_add(1)),
+            Direction::Down => (Some(self.x), self.y.checked_sub(1)),
+            Direction::Right => (self.x.checked_add(1), Some(self.y)),
+            Direction::Left => (self.x.checked_sub(1), Some(self.y)),
+        };
+        Some(Pos::new(x?, y?))
+    }
+
     /// Returns the 4 edges that surround the given cell, in this order:
     /// Up, Down, Right, Left
     #[inline(always)]
@@ -125,11 +147,59 @@ impl Display for Direction {
     }
 }
 
+/// Reason why a path is malformed or can't be drawn on a puzzle
+#[derive(Clone, Debug, PartialEq)]
+pub enum PathError {
+    /// The path has no vertices
+    Empty,
+    /// The text is not of the form `(x, y) DIRECTIONS`
+    Malformed(String),
+    /// The character is not one of `U`, `D`, `R` or `L`
+    InvalidDirection(char),
+    /// Two consecutive vertices of the path are not adjacent
+    NotAdjacent(Pos, Pos),
+    /// The path does not begin on a start vertex
+    NotAStart(Pos),
+    /// The path does not finish on an end vertex
+    NotAnEnd(Pos),
+    /// The vertex is outside of the puzzle
+    OutOfBounds(Pos),
+    /// The path goes through this vertex more than once
+    SelfIntersection(Pos),
+    /// The path goes through a broken edge
+    BlockedEdge(EdgePos),
+    /// Moving from the vertex in the direction goes past the range of coordinates
+    Overflow(Pos, Direction),
+}
+
+impl Display for PathError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PathError::Empty => write!(f, "path is empty"),
+            PathError::Malformed(text) => write!(f, "malformed path {text:?}"),
+            PathError::InvalidDirection(c) => write!(f, "invalid direction {c:?}"),
+            PathError::NotAdjacent(a, b) => write!(f, "{a} and {b} are not adjacent"),
+            PathError::NotAStart(pos) => write!(f, "path starts at {pos}, which is not a start"),
+            PathError::NotAnEnd(pos) => write!(f, "path ends at {pos}, which is not an end"),
+            PathError::OutOfBounds(pos) => write!(f, "{pos} is outside of the puzzle"),
+            PathError::SelfIntersection(pos) => write!(f, "path goes through {pos} twice"),
+            PathError::BlockedEdge(edge) => {
+                write!(f, "edge {} {} is broken", edge.pos, edge.dir)
+            }
+            PathError::Overflow(pos, dir) => {
+                write!(f, "moving {dir} from {pos} overflows the coordinates")
+            }
+        }
+    }
+}
+
+impl std::error::Error for PathError {}
+
 #[derive(Clone, Debug, PartialEq)]
 pub struct SolutionPath(Vec<Pos>);
 
 impl SolutionPath {
-    pub fn new(start: Pos, dirs: String) -> Result<Self, ()> {
+    pub fn new(start: Pos, dirs: String) -> Result<Self, PathError> {
         let mut res = vec![start];
         let mut prev = start;
 
@@ -139,10 +209,12 @@ impl SolutionPath {
                 'D' => Direction::Down,
                 'R' => Direction::Right,
                 'L' => Direction::Left,
-                _ => return Err(()),
+                _ => return Err(PathError::InvalidDirection(char)),
             };
 
-            let next = prev.move_direction(dir);
+            let next = prev
+                .checked_move_direction(dir)
+                .ok_or(PathError::Overflow(prev, dir))?;
             res.push(next);
             prev = next;
         }
@@ -150,15 +222,109 @@ impl SolutionPath {
         Ok(Self(res))
     }
 
-    pub fn try_as_edge_path(&self) -> Result<Vec<EdgePos>, ()> {
-        let mut path_edges = Vec::with_capacity(self.len() - 1);
+    pub fn try_as_edge_path(&self) -> Result<Vec<EdgePos>, PathError> {
+        let mut path_edges = Vec::with_capacity(self.len().saturating_sub(1));
         for window in self.windows(2) {
-            let dir = window[0].get_direction_to(&window[1]).ok_or(())?;
+            let dir = window[0]
+                .get_direction_to(&window[1])
+                .ok_or(PathError::NotAdjacent(window[0], window[1]))?;
             path_edges.push(EdgePos::new(window[0].x, window[0].y, dir))
         }
 
         Ok(path_edges)
     }
+
+    /// Same as [`SolutionPath::try_as_edge_path`], as a set sized for the puzzle.
+    /// Also fails if the path leaves the puzzle.
+    pub fn try_as_edge_set(&self, puzzle: &Puzzle) -> Result<EdgeSet, PathError> {
+        if let Some(pos) = self.iter().find(|pos| !puzzle.contains_vertex(pos)) {
+            return Err(PathError::OutOfBounds(*pos));
+        }
+
+        let mut path_edges = EdgeSet::new(puzzle);
+        for edge in self.try_as_edge_path()? {
+            path_edges.insert(&edge);
+        }
+
+        Ok(path_edges)
+    }
+
+    /// Returns the set of vertices of the path that are in the puzzle
+    pub fn as_vertex_set(&self, puzzle: &Puzzle) -> VertexSet {
+        let mut path_positions = VertexSet::new(puzzle);
+        for pos in self.iter().filter(|pos| puzzle.contains_vertex(pos)) {
+            path_positions.insert(pos);
+        }
+
+        path_positions
+    }
+
+    /// Check that the path can be drawn on the puzzle, from a start to an end.
+    /// Returns the first problem found when walking along the path.
+    pub fn validate_against(&self, puzzle: &Puzzle) -> Result<(), PathError> {
+        match self.errors_against(puzzle).into_iter().next() {
+            Some(err) => Err(err),
+            None => Ok(()),
+        }
+    }
+
+    /// Same as [`SolutionPath::validate_against`], except that the path
+    /// doesn't have to reach an end yet
+    pub fn validate_prefix_against(&self, puzzle: &Puzzle) -> Result<(), PathError> {
+        match self.prefix_errors_against(puzzle).into_iter().next() {
+            Some(err) => Err(err),
+            None => Ok(()),
+        }
+    }
+
+    /// Lists every problem that keeps the path from being drawn on the puzzle
+    /// from a start to an end, in the order they are found along the path
+    pub fn errors_against(&self, puzzle: &Puzzle) -> Vec<PathError> {
+        let mut errors = self.prefix_errors_against(puzzle);
+        if let Some(last) = self.last() {
+            if !puzzle.ends.contains(last) {
+                errors.push(PathError::NotAnEnd(*last));
+            }
+        }
+
+        errors
+    }
+
+    /// Same as [`SolutionPath::errors_against`], without the end
+    fn prefix_errors_against(&self, puzzle: &Puzzle) -> Vec<PathError> {
+        let Some(first) = self.first() else {
+            return vec![PathError::Empty];
+        };
+
+        let mut errors = vec![];
+        if !puzzle.starts.contains(first) {
+            errors.push(PathError::NotAStart(*first));
+        }
+
+        let mut visited = HashSet::new();
+        for (idx, pos) in self.iter().enumerate() {
+            if !puzzle.contains_vertex(pos) {
+                errors.push(PathError::OutOfBounds(*pos));
+            }
+            if idx > 0 {
+                let prev = self[idx - 1];
+                match prev.get_direction_to(pos) {
+                    Some(dir) => {
+                        let edge = EdgePos::new(prev.x, prev.y, dir);
+                        if puzzle.is_blocked(&edge) {
+                            errors.push(PathError::BlockedEdge(edge));
+                        }
+                    }
+                    None => errors.push(PathError::NotAdjacent(prev, *pos)),
+                }
+            }
+            if !visited.insert(*pos) {
+                errors.push(PathError::SelfIntersection(*pos));
+            }
+        }
+
+        errors
+    }
 }
 
 impl From<Vec<Pos>> for SolutionPath {
@@ -191,6 +357,33 @@ impl Display for SolutionPath {
     }
 }
 
+/// Parses the text produced by the [`Display`] implementation,
+/// e.g. `(0, 0) UURR` or `Empty solution`
+impl FromStr for SolutionPath {
+    type Err = PathError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "Empty solution" {
+            return Ok(Self(vec![]));
+        }
+
+        let malformed = || PathError::Malformed(s.to_string());
+        let (x, rest) = s
+            .strip_prefix('(')
+            .and_then(|rest| rest.split_once(", "))
+            .ok_or_else(malformed)?;
+        let (y, dirs) = rest.split_once(") ").ok_or_else(malformed)?;
+
+        // Only accept the exact way coordinates are printed (no sign, no padding)
+        let parse_coord = |text: &str| match text.parse::<Coord>() {
+            Ok(coord) if coord.to_string() == text => Ok(coord),
+            _ => Err(malformed()),
+        };
+
+        Self::new(Pos::new(parse_coord(x)?, parse_coord(y)?), dirs.to_string())
+    }
+}
+
 impl Deref for SolutionPath {
     type Target = Vec<Pos>;
 
@@ -212,7 +405,7 @@ pub struct EdgePos {
 }
 
 impl EdgePos {
-    pub const fn new(x: i8, y: i8, dir: Direction) -> Self {
+    pub const fn new(x: Coord, y: Coord, dir: Direction) -> Self {
         Self {
             pos: Pos { x, y },
             dir,
@@ -237,7 +430,7 @@ impl EdgePos {
         [self.pos, self.pos.move_direction(self.dir)]
     }
 
-    fn normalize(&self) -> Self {
+    pub(crate) fn normalize(&self) -> Self {
         let mut res = *self;
         match self.dir {
             Direction::Down => {
@@ -254,7 +447,7 @@ impl EdgePos {
     }
 
     #[inline(always)]
-    fn true_eq(self, other: &Self) -> bool {
+    pub(crate) fn true_eq(self, other: &Self) -> bool {
         self.pos == other.pos && self.dir == other.dir
     }
 }
@@ -291,7 +484,7 @@ impl From<u32> for Poly {
         for x in 0..4 {
             for y in 0..4 {
                 if (value & (1 << (x * 4 + 3 - y))) != 0 {
-                    minos.push(Pos::new(x as i8, y as i8))
+                    minos.push(Pos::new(x, y))
                 }
             }
         }
@@ -332,7 +525,7 @@ impl Poly {
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum CellType {
     /// Empty cell that has no constaints
     Empty,
@@ -354,27 +547,131 @@ pub enum CellType {
     Canceller(Color),
 }
 
+/// Paths that split the puzzle into the same areas
+#[derive(Clone, Debug, PartialEq)]
+pub struct RegionClass {
+    /// Shortest path of the class, the first one found if there are several
+    pub representative: SolutionPath,
+    /// Number of paths in the class
+    pub size: usize,
+}
+
 pub struct Area {
     /// All the cells contained in the area
-    cells: HashSet<Pos>,
+    pub cells: HashSet<Pos>,
 
     /// An edge is in the area if both cells surrounding it are either:
     /// - in the area
     /// - considered "outside" the puzzle
     ///
     /// and it is not part of the solution path.
-    edges: HashSet<EdgePos>,
+    pub edges: HashSet<EdgePos>,
 
     /// A corner is in the area if:
     /// - the 4 adjacent cells must be either in the area or outside the puzzle
     /// - the pos is not part of the path
-    corners: HashSet<Pos>,
+    pub corners: HashSet<Pos>,
 }
 
+/// Rule broken by a candidate solution
+#[derive(Clone, Debug, PartialEq)]
+pub enum ViolationKind {
+    /// The path itself can't be drawn from a start to an end
+    Path(PathError),
+    /// The symbol in the cell is not satisfied
+    Cell(Pos, CellType),
+    /// The stone on this vertex is not crossed by the path
+    VertexStone(Pos),
+    /// The stone on this edge is not crossed by the path
+    EdgeStone(EdgePos),
+}
+
+/// A failing constraint, as reported by [`Puzzle::validate`]
+#[derive(Clone, Debug, PartialEq)]
+pub struct Violation {
+    pub kind: ViolationKind,
+    /// Index of the area the violation is in, as returned by [`Puzzle::areas`].
+    /// This is `None` when the path itself is malformed.
+    pub area: Option<usize>,
+    /// Cancellers of the area that were spent on other symbols
+    pub cancels_used: Vec<Pos>,
+}
+
+/// Structural problem in a puzzle, as reported by [`Puzzle::validate_structure`]
+#[derive(Clone, Debug, PartialEq)]
+pub enum PuzzleError {
+    /// The puzzle needs to be at least one cell wide and high,
+    /// and at most [`Puzzle::MAX_SIZE`] cells
+    InvalidSize { width: Coord, height: Coord },
+    /// The puzzle has no start vertex
+    NoStart,
+    /// The puzzle has no end vertex
+    NoEnd,
+    /// The start is not a vertex of the puzzle
+    StartOutOfBounds(Pos),
+    /// The end is not a vertex of the puzzle
+    EndOutOfBounds(Pos),
+    /// The stone is not on a vertex of the puzzle
+    VertexStoneOutOfBounds(Pos),
+    /// The stone is not on an edge of the puzzle
+    EdgeStoneOutOfBounds(EdgePos),
+    /// The stone is on a broken edge, so it can never be crossed
+    StoneOnBlockedEdge(EdgePos),
+    /// The symbol is in a cell that is outside of the puzzle
+    SymbolOutside(Pos),
+    /// The cell holds more than one symbol
+    MultipleSymbols(Pos),
+    /// Triangles come in groups of 1 to 3
+    InvalidTriangleCount(Pos, u8),
+    /// The polyomino has no blocks
+    EmptyPoly(Pos),
+}
+
+impl Display for PuzzleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PuzzleError::InvalidSize { width, height } => {
+                write!(f, "invalid puzzle size {width}x{height}")
+            }
+            PuzzleError::NoStart => write!(f, "puzzle has no start"),
+            PuzzleError::NoEnd => write!(f, "puzzle has no end"),
+            PuzzleError::StartOutOfBounds(pos) => write!(f, "start {pos} is outside the puzzle"),
+            PuzzleError::EndOutOfBounds(pos) => write!(f, "end {pos} is outside the puzzle"),
+            PuzzleError::VertexStoneOutOfBounds(pos) => {
+                write!(f, "stone {pos} is outside the puzzle")
+            }
+            PuzzleError::EdgeStoneOutOfBounds(edge) => {
+                write!(
+                    f,
+                    "stone on edge {} {} is outside the puzzle",
+                    edge.pos, edge.dir
+                )
+            }
+            PuzzleError::StoneOnBlockedEdge(edge) => {
+                write!(
+                    f,
+                    "stone on edge {} {} is on a broken edge",
+                    edge.pos, edge.dir
+                )
+            }
+            PuzzleError::SymbolOutside(pos) => {
+                write!(f, "symbol in cell {pos} is outside the puzzle")
+            }
+            PuzzleError::MultipleSymbols(pos) => write!(f, "cell {pos} holds several symbols"),
+            PuzzleError::InvalidTriangleCount(pos, count) => {
+                write!(f, "cell {pos} has {count} triangles")
+            }
+            PuzzleError::EmptyPoly(pos) => write!(f, "polyomino in cell {pos} has no blocks"),
+        }
+    }
+}
+
+impl std::error::Error for PuzzleError {}
+
 #[derive(Clone, Debug)]
 pub struct Puzzle {
-    pub width: i8,
-    pub height: i8,
+    pub width: Coord,
+    pub height: Coord,
 
     /// List of start vertices of the puzzle
     pub starts: Vec<Pos>,
@@ -422,9 +719,13 @@ impl Default for Puzzle {
 }
 
 impl Puzzle {
+    /// Maximum width and height of a puzzle, in cells. This leaves plenty of
+    /// headroom in [`Coord`] for positions computed around the grid.
+    pub const MAX_SIZE: Coord = Coord::MAX / 4;
+
     /// Returns a new puzzle with the given dimentions, with the start in the
     /// bottom left and then end in the bottom right
-    pub fn default_with_size(width: i8, height: i8) -> Self {
+    pub fn default_with_size(width: Coord, height: Coord) -> Self {
         Self {
             width,
             height,
@@ -463,6 +764,105 @@ impl Puzzle {
         self.blocked_edges.contains(&edge.normalize())
     }
 
+    /// Check that the puzzle makes sense, and return every problem found.
+    /// A malformed puzzle usually just has no solutions, which is hard to debug.
+    pub fn validate_structure(&self) -> Result<(), Vec<PuzzleError>> {
+        let mut errors = vec![];
+        let sorted = |positions: Vec<Pos>| {
+            let mut positions = positions;
+            positions.sort_by_key(|p| (p.x, p.y));
+            positions
+        };
+        let sorted_edges = |edges: Vec<EdgePos>| {
+            let mut edges: Vec<_> = edges.iter().map(EdgePos::normalize).collect();
+            edges.sort_by_key(|e| (e.pos.x, e.pos.y, e.dir as u8));
+            edges
+        };
+
+        if !(1..=Self::MAX_SIZE).contains(&self.width)
+            || !(1..=Self::MAX_SIZE).contains(&self.height)
+        {
+            errors.push(PuzzleError::InvalidSize {
+                width: self.width,
+                height: self.height,
+            });
+        }
+
+        // Starts and ends
+        if self.starts.is_empty() {
+            errors.push(PuzzleError::NoStart);
+        }
+        if self.ends.is_empty() {
+            errors.push(PuzzleError::NoEnd);
+        }
+        for start in self.starts.iter().filter(|p| !self.contains_vertex(p)) {
+            errors.push(PuzzleError::StartOutOfBounds(*start));
+        }
+        for end in self.ends.iter().filter(|p| !self.contains_vertex(p)) {
+            errors.push(PuzzleError::EndOutOfBounds(*end));
+        }
+
+        // Stones
+        for stone in sorted(self.vertex_stones.iter().copied().collect()) {
+            if !self.contains_vertex(&stone) {
+                errors.push(PuzzleError::VertexStoneOutOfBounds(stone));
+            }
+        }
+        for stone in sorted_edges(self.edge_stones.iter().copied().collect()) {
+            if !stone
+                .get_neighbouring_corners()
+                .iter()
+                .all(|corner| self.contains_vertex(corner))
+            {
+                errors.push(PuzzleError::EdgeStoneOutOfBounds(stone));
+            } else if self.is_blocked(&stone) {
+                errors.push(PuzzleError::StoneOnBlockedEdge(stone));
+            }
+        }
+
+        // Cell symbols
+        let symbol_maps: [Vec<Pos>; 6] = [
+            self.squares.keys().copied().collect(),
+            self.stars.keys().copied().collect(),
+            self.triangles.keys().copied().collect(),
+            self.polys.keys().copied().collect(),
+            self.ylops.keys().copied().collect(),
+            self.cancels.keys().copied().collect(),
+        ];
+        let mut symbol_counts: HashMap<Pos, usize> = HashMap::new();
+        for pos in symbol_maps.iter().flatten() {
+            *symbol_counts.entry(*pos).or_insert(0) += 1;
+        }
+        for pos in sorted(symbol_counts.keys().copied().collect()) {
+            if !self.contains_cell(&pos) {
+                errors.push(PuzzleError::SymbolOutside(pos));
+            }
+            if symbol_counts[&pos] > 1 {
+                errors.push(PuzzleError::MultipleSymbols(pos));
+            }
+        }
+        for pos in sorted(self.triangles.keys().copied().collect()) {
+            if !(1..=3).contains(&self.triangles[&pos]) {
+                errors.push(PuzzleError::InvalidTriangleCount(pos, self.triangles[&pos]));
+            }
+        }
+        let empty_polys = self
+            .polys
+            .iter()
+            .chain(self.ylops.iter())
+            .filter(|(_, poly)| poly.minos.is_empty())
+            .map(|(pos, _)| *pos);
+        for pos in sorted(empty_polys.collect()) {
+            errors.push(PuzzleError::EmptyPoly(pos));
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
     /// Check that the proposed solution is valid
     /// This is quite expensive, and should only be used
     /// for testing, as a ground truth
@@ -471,24 +871,35 @@ impl Puzzle {
             return false;
         }
 
-        // Check start and end
-        if !self.starts.contains(path.first().unwrap()) {
+        // Check start, end, and that the path can actually be drawn
+        if path.validate_against(self).is_err() {
             return false;
         }
-        if !self.ends.contains(path.last().unwrap()) {
+
+        let path_positions = path.as_vertex_set(self);
+        let Ok(path_edges) = path.try_as_edge_set(self) else {
+            return false;
+        };
+
+        if !self
+            .stones_outside_areas(&path_positions, &path_edges)
+            .is_empty()
+        {
             return false;
         }
 
-        let mut all_visited = HashSet::new();
+        let mut all_visited = CellSet::new(self);
         for x in 0..self.width {
             for y in 0..self.height {
                 let pos = Pos::new(x, y);
                 if !all_visited.contains(&pos) {
-                    let area = self.floodfill(pos, path);
+                    let area = self.floodfill_with(pos, &path_positions, &path_edges);
                     if !self.is_valid(path, &area) {
                         return false;
                     }
-                    all_visited.extend(area.cells);
+                    for cell in area.cells {
+                        all_visited.insert(&cell);
+                    }
                 }
             }
         }
@@ -496,7 +907,207 @@ impl Puzzle {
         true
     }
 
+    /// Check the proposed solution and list every constraint it breaks
+    ///
+    /// Unlike [`Puzzle::is_solution`], this does not stop at the first error.
+    /// A path that can't be drawn only gets its path errors, as its areas would
+    /// be meaningless. In an invalid area, cancellers are spent greedily on the
+    /// failing symbols, and whatever is left over is reported along with the
+    /// cancellers used.
+    pub fn validate(&self, path: &SolutionPath) -> Result<(), Vec<Violation>> {
+        let mut violations: Vec<_> = path
+            .errors_against(self)
+            .into_iter()
+            .map(|err| Violation {
+                kind: ViolationKind::Path(err),
+                area: None,
+                cancels_used: vec![],
+            })
+            .collect();
+
+        // The areas of a path that can't be drawn would be meaningless
+        if !violations.is_empty() {
+            return Err(violations);
+        }
+        let path_edges = path
+            .try_as_edge_set(self)
+            .expect("paths that can be drawn have no holes");
+
+        let path_positions = path.as_vertex_set(self);
+        for kind in self.stones_outside_areas(&path_positions, &path_edges) {
+            violations.push(Violation {
+                kind,
+                area: None,
+                cancels_used: vec![],
+            });
+        }
+
+        let areas = self.areas_with(&path_positions, &path_edges);
+        for (idx, area) in areas.iter().enumerate() {
+            if self.is_valid(path, area) {
+                continue;
+            }
+
+            let mut cancels_left: Vec<Pos> = self
+                .cancels
+                .keys()
+                .filter(|p| area.cells.contains(p))
+                .copied()
+                .collect();
+            cancels_left.sort_by_key(|p| (p.x, p.y));
+
+            // Spend the cancellers one by one on the first failing symbol
+            let mut puzzle = self.clone();
+            let mut cancels_used = vec![];
+            let mut errors = puzzle.area_violations(&path_edges, area);
+            while !errors.is_empty() && !cancels_left.is_empty() {
+                cancels_used.push(cancels_left.remove(0));
+                puzzle.remove_constraint(&errors[0]);
+                errors = puzzle.area_violations(&path_edges, area);
+            }
+
+            if errors.is_empty() {
+                // Cancellers that have nothing left to cancel are errors themselves.
+                // If they were all used but the area is still invalid, blame them all.
+                if cancels_left.is_empty() {
+                    cancels_left = std::mem::take(&mut cancels_used);
+                }
+                errors = cancels_left
+                    .iter()
+                    .map(|&p| ViolationKind::Cell(p, CellType::Canceller(self.cancels[&p])))
+                    .collect();
+            }
+
+            violations.extend(errors.into_iter().map(|kind| Violation {
+                kind,
+                area: Some(idx),
+                cancels_used: cancels_used.clone(),
+            }));
+        }
+
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(violations)
+        }
+    }
+
+    /// Lists the constraints of the area that are not satisfied, ignoring cancellers
+    fn area_violations(&self, path_edges: &EdgeSet, area: &Area) -> Vec<ViolationKind> {
+        let mut cells: Vec<Pos> = area.cells.iter().copied().collect();
+        cells.sort_by_key(|p| (p.x, p.y));
+        let mut res = vec![];
+
+        // Stones
+        let mut corners: Vec<Pos> = area
+            .corners
+            .iter()
+            .filter(|c| self.vertex_stones.contains(c))
+            .copied()
+            .collect();
+        corners.sort_by_key(|p| (p.x, p.y));
+        res.extend(corners.into_iter().map(ViolationKind::VertexStone));
+        let mut edges: Vec<EdgePos> = area
+            .edges
+            .iter()
+            .filter(|e| self.edge_stones.contains(e))
+            .map(|e| e.normalize())
+            .collect();
+        edges.sort_by_key(|e| (e.pos.x, e.pos.y, e.dir as u8));
+        res.extend(edges.into_iter().map(ViolationKind::EdgeStone));
+
+        // Triangles
+        for cell in &cells {
+            if let Some(&count) = self.triangles.get(cell) {
+                let touching = cell
+                    .get_cell_edges()
+                    .iter()
+                    .filter(|edge| path_edges.contains(edge))
+                    .count();
+                if count as usize != touching {
+                    res.push(ViolationKind::Cell(*cell, CellType::Triangle(count)));
+                }
+            }
+        }
+
+        // Squares: keep the most common color, the others are wrong
+        let mut square_counts: HashMap<Color, usize> = HashMap::new();
+        for col in cells.iter().filter_map(|pos| self.squares.get(pos)) {
+            *square_counts.entry(*col).or_insert(0) += 1;
+        }
+        let majority = square_counts
+            .iter()
+            .max_by_key(|(&col, &count)| (count, std::cmp::Reverse(col)))
+            .map(|(&col, _)| col);
+        for cell in &cells {
+            match self.squares.get(cell) {
+                Some(&col) if Some(col) != majority => {
+                    res.push(ViolationKind::Cell(*cell, CellType::Square(col)))
+                }
+                _ => {}
+            }
+        }
+
+        // Stars need exactly one other symbol of their color
+        for cell in &cells {
+            if let Some(&col) = self.stars.get(cell) {
+                let same_color = cells
+                    .iter()
+                    .filter(|p| {
+                        self.stars.get(p) == Some(&col) || self.squares.get(p) == Some(&col)
+                    })
+                    .count();
+                if same_color != 2 {
+                    res.push(ViolationKind::Cell(*cell, CellType::Star(col)));
+                }
+            }
+        }
+
+        // Polys and ylops can only be blamed together
+        if !self.check_tetris(&area.cells) {
+            for cell in &cells {
+                if let Some(poly) = self.polys.get(cell) {
+                    res.push(ViolationKind::Cell(*cell, CellType::Poly(poly.clone())));
+                }
+                if let Some(ylop) = self.ylops.get(cell) {
+                    res.push(ViolationKind::Cell(*cell, CellType::Ylop(ylop.clone())));
+                }
+            }
+        }
+
+        res
+    }
+
+    /// Removes the symbol responsible for the given violation from the puzzle
+    fn remove_constraint(&mut self, kind: &ViolationKind) {
+        match kind {
+            ViolationKind::Cell(pos, _) => {
+                self.squares.remove(pos);
+                self.stars.remove(pos);
+                self.triangles.remove(pos);
+                self.polys.remove(pos);
+                self.ylops.remove(pos);
+            }
+            ViolationKind::VertexStone(pos) => {
+                self.vertex_stones.remove(pos);
+            }
+            ViolationKind::EdgeStone(edge) => {
+                self.edge_stones.remove(edge);
+            }
+            ViolationKind::Path(_) => {}
+        }
+    }
+
     /// Returns true if the given area is valid
+    pub fn is_valid(&self, path: &SolutionPath, area: &Area) -> bool {
+        let Ok(path_edges) = path.try_as_edge_set(self) else {
+            return false;
+        };
+
+        self.is_area_valid(&path_edges, area)
+    }
+
+    /// Returns true if the given area is valid, with the path given as a set of edges
     ///
     /// This function check if an area is valid in the following order
     /// - Check independant position based constraints (triangles and hexagons)
@@ -513,11 +1124,7 @@ impl Puzzle {
     /// this would also allow recursing on the area instead of the whole ass puzzle, and avoid duplicate
     /// computation!
     /// it would make the actual checks a little clunkier though
-    pub fn is_valid(&self, path: &SolutionPath, area: &Area) -> bool {
-        let Ok(path_edges) = path.try_as_edge_path() else {
-            return false;
-        };
-
+    pub fn is_area_valid(&self, path_edges: &EdgeSet, area: &Area) -> bool {
         let mut puzzle_without_obvious_cancels = self.clone();
 
         let mut cancels_in_area: Vec<_> = self
@@ -603,7 +1210,7 @@ impl Puzzle {
 
                 // If the area is valid when the canceller
                 // is ignored, then its bad (does this hold true with multiple cancellers?)
-                if new_puzzle.is_valid(path, area) {
+                if new_puzzle.is_area_valid(path_edges, area) {
                     return false;
                 }
 
@@ -614,7 +1221,7 @@ impl Puzzle {
                     || new_puzzle.ylops.remove(pos).is_some()
                     || new_puzzle.cancels.remove(pos).is_some()
                 {
-                    new_puzzle.is_valid(path, area)
+                    new_puzzle.is_area_valid(path_edges, area)
                 } else {
                     false
                 };
@@ -682,7 +1289,7 @@ impl Puzzle {
         let elit_count = ylops.iter().fold(0, |acc, ylop| acc + ylop.minos.len());
 
         // If the tile counts don't even match the area, don't bother doing anything else
-        if tile_count - elit_count != area.len() {
+        if tile_count != area.len() + elit_count {
             return false;
         }
 
@@ -704,8 +1311,11 @@ impl Puzzle {
             return area.values().all(|&cover_count| cover_count == 1);
         }
 
-        // Find a square that is not covered
-        let square = area.iter().find(|(_pos, &value)| value < 1).unwrap();
+        // Find a square that is not covered, if there is none the remaining
+        // polys can't be placed
+        let Some(square) = area.iter().find(|(_pos, &value)| value < 1) else {
+            return false;
+        };
 
         for (poly_idx, poly) in polys.iter().enumerate() {
             // For every mino, attempt to place it at the selected square
@@ -735,71 +1345,171 @@ impl Puzzle {
         false
     }
 
-    /// Returns the list of connected cells starting from `pos`, delimited by `edges`
-    pub fn floodfill(&self, pos: Pos, path: &SolutionPath) -> Area {
+    /// Returns all the areas delimited by the path, in the order their first
+    /// cell is found when scanning columns from left to right.
+    /// Fails if the path has holes in it.
+    pub fn areas(&self, path: &SolutionPath) -> Result<Vec<Area>, PathError> {
+        let path_edges = path.try_as_edge_set(self)?;
+        Ok(self.areas_with(&path.as_vertex_set(self), &path_edges))
+    }
+
+    /// Same as [`Puzzle::areas`], with the path already converted to sets
+    fn areas_with(&self, path_positions: &VertexSet, path_edges: &EdgeSet) -> Vec<Area> {
+        let mut res = vec![];
+        let mut all_visited = CellSet::new(self);
+        for x in 0..self.width {
+            for y in 0..self.height {
+                let pos = Pos::new(x, y);
+                if self.contains_cell(&pos) && !all_visited.contains(&pos) {
+                    let area = self.floodfill_with(pos, path_positions, path_edges);
+                    for cell in area.cells.iter() {
+                        all_visited.insert(cell);
+                    }
+                    res.push(area);
+                }
+            }
+        }
+
+        res
+    }
+
+    /// Returns the index in [`Puzzle::areas`] of the area of each cell, column by column.
+    /// Two paths split the puzzle into the same areas when their partitions are equal.
+    /// Fails if the path can't be drawn from a start to an end.
+    pub fn partition(&self, path: &SolutionPath) -> Result<Vec<Option<usize>>, PathError> {
+        path.validate_against(self)?;
+
+        let mut res = vec![None; self.width as usize * self.height as usize];
+        for (idx, area) in self.areas(path)?.iter().enumerate() {
+            for cell in &area.cells {
+                res[cell.x as usize * self.height as usize + cell.y as usize] = Some(idx);
+            }
+        }
+
+        Ok(res)
+    }
+
+    /// Groups the paths by the areas they split the puzzle into, see [`Puzzle::partition`].
+    /// Classes are in the order of their first path. Fails on the first path that
+    /// can't be drawn from a start to an end.
+    pub fn group_by_regions(&self, paths: &[SolutionPath]) -> Result<Vec<RegionClass>, PathError> {
+        let mut res: Vec<RegionClass> = vec![];
+        let mut classes: HashMap<_, usize> = HashMap::new();
+        for path in paths {
+            match classes.entry(self.partition(path)?) {
+                Entry::Occupied(entry) => {
+                    let class = &mut res[*entry.get()];
+                    class.size += 1;
+                    if path.len() < class.representative.len() {
+                        class.representative = path.clone();
+                    }
+                }
+                Entry::Vacant(entry) => {
+                    entry.insert(res.len());
+                    res.push(RegionClass {
+                        representative: path.clone(),
+                        size: 1,
+                    });
+                }
+            }
+        }
+
+        Ok(res)
+    }
+
+    /// Returns the list of connected cells starting from `pos`, delimited by the path.
+    /// Fails if the path has holes in it.
+    pub fn floodfill(&self, pos: Pos, path: &SolutionPath) -> Result<Area, PathError> {
+        let path_edges = path.try_as_edge_set(self)?;
+        Ok(self.floodfill_with(pos, &path.as_vertex_set(self), &path_edges))
+    }
+
+    /// Same as [`Puzzle::floodfill`], with the path already converted to sets
+    fn floodfill_with(&self, pos: Pos, path_positions: &VertexSet, path_edges: &EdgeSet) -> Area {
         let mut cells = HashSet::new();
-        let mut edges = HashSet::new();
-        let mut corners = HashSet::new();
+        let mut to_visit = vec![pos];
 
-        self.floodfill_recur(
-            pos,
-            path,
-            &path.try_as_edge_path().unwrap(),
-            &mut cells,
-            &mut edges,
-            &mut corners,
-        );
+        while let Some(pos) = to_visit.pop() {
+            // Skip the cell if it is outside of the puzzle or
+            // if the cell was already in the area
+            if !self.contains_cell(&pos) || !cells.insert(pos) {
+                continue;
+            }
 
-        Area {
-            cells,
-            edges,
-            corners,
+            for dir in Direction::VARIANTS {
+                if !path_edges.contains(&pos.get_cell_edge_in_direction(dir)) {
+                    to_visit.push(pos.move_direction(dir));
+                }
+            }
+        }
+
+        self.area_from_cells(cells, path_positions, path_edges)
+    }
+
+    /// Returns the stones that the path missed among the ones that don't touch
+    /// any cell of the puzzle. These are not part of any area, so checking
+    /// the areas doesn't cover them.
+    pub fn stones_outside_areas(
+        &self,
+        path_positions: &VertexSet,
+        path_edges: &EdgeSet,
+    ) -> Vec<ViolationKind> {
+        let mut res = vec![];
+
+        let mut vertex_stones: Vec<_> = self.vertex_stones.iter().copied().collect();
+        vertex_stones.sort_by_key(|p| (p.x, p.y));
+        for stone in vertex_stones {
+            let cells = [(-1, -1), (-1, 0), (0, -1), (0, 0)]
+                .map(|(dx, dy)| Pos::new(stone.x + dx, stone.y + dy));
+            if !path_positions.contains(&stone) && !cells.iter().any(|c| self.contains_cell(c)) {
+                res.push(ViolationKind::VertexStone(stone));
+            }
+        }
+
+        let mut edge_stones: Vec<_> = self.edge_stones.iter().map(EdgePos::normalize).collect();
+        edge_stones.sort_by_key(|e| (e.pos.x, e.pos.y, e.dir as u8));
+        for stone in edge_stones {
+            let (left, right) = stone.get_neighbouring_cells();
+            if !path_edges.contains(&stone)
+                && !self.contains_cell(&left)
+                && !self.contains_cell(&right)
+            {
+                res.push(ViolationKind::EdgeStone(stone));
+            }
         }
+
+        res
     }
 
-    pub fn floodfill_recur(
+    /// Builds the area made of the given cells, adding the edges and
+    /// corners around them that are not part of the path
+    pub fn area_from_cells(
         &self,
-        pos: Pos,
-        path_positions: &Vec<Pos>,
-        path_edges: &Vec<EdgePos>,
-        area_cells: &mut HashSet<Pos>,
-        area_edges: &mut HashSet<EdgePos>,
-        area_corners: &mut HashSet<Pos>,
-    ) {
-        // TODO: maybe switch the edge list for Hashset?
-
-        // Return immediately if cell is outside of the puzzle or
-        // if the cell was already in the area
-        if !self.contains_cell(&pos) || !area_cells.insert(pos) {
-            return;
-        }
-
-        for dir in Direction::VARIANTS {
-            let crossing_edge = pos.get_cell_edge_in_direction(dir);
-
-            if !path_edges.contains(&crossing_edge) {
-                // pos is in the area and is not an outside cell
-                // the crossing edge is not part of the path
-                // therefore it must be in the area
-                area_edges.insert(crossing_edge);
-
-                // See if the corners are in the area
-                for corner in crossing_edge.get_neighbouring_corners() {
-                    if !path_positions.contains(&corner) {
-                        area_corners.insert(corner);
+        cells: HashSet<Pos>,
+        path_positions: &VertexSet,
+        path_edges: &EdgeSet,
+    ) -> Area {
+        let mut edges = HashSet::new();
+        let mut corners = HashSet::new();
+
+        for cell in &cells {
+            for edge in cell.get_cell_edges() {
+                if !path_edges.contains(&edge) {
+                    edges.insert(edge);
+                    for corner in edge.get_neighbouring_corners() {
+                        if !path_positions.contains(&corner) {
+                            corners.insert(corner);
+                        }
                     }
                 }
-
-                self.floodfill_recur(
-                    pos.move_direction(dir),
-                    path_positions,
-                    path_edges,
-                    area_cells,
-                    area_edges,
-                    area_corners,
-                );
             }
         }
+
+        Area {
+            cells,
+            edges,
+            corners,
+        }
     }
 
     /// Creates a Puzzle from jbdarkid's json format
@@ -807,7 +1517,7 @@ impl Puzzle {
     /// to two end/start on each end of the edge, and a blocked
     /// edge separating them
     pub fn from_json(src: &str) -> Result<Self, String> {
-        let data = json::parse(src).unwrap();
+        let data = json::parse(src).map_err(|err| err.to_string())?;
 
         let width = data["width"]
             .as_usize()
@@ -818,9 +1528,17 @@ impl Puzzle {
             .ok_or("Failed to decode puzzle height")?
             / 2;
 
+        // Checked here so that the casts below can't truncate
+        if width > Self::MAX_SIZE as usize || height > Self::MAX_SIZE as usize {
+            return Err(format!(
+                "Puzzle is too large ({width}x{height}, max is {})",
+                Self::MAX_SIZE
+            ));
+        }
+
         let mut puzzle = Puzzle {
-            width: width as i8,
-            height: height as i8,
+            width: width as Coord,
+            height: height as Coord,
             starts: vec![],
             ends: vec![],
             ..Default::default()
@@ -834,44 +1552,57 @@ impl Puzzle {
             for y in 0..=height {
                 let cell = &data["grid"][x * 2][(height - y) * 2];
                 if cell["start"] == true {
-                    puzzle.starts.push(Pos::new(x as i8, y as i8));
+                    puzzle.starts.push(Pos::new(x as Coord, y as Coord));
                 }
 
                 if cell["end"].is_string() {
-                    puzzle.ends.push(Pos::new(x as i8, y as i8));
+                    puzzle.ends.push(Pos::new(x as Coord, y as Coord));
                 }
 
                 if cell["dot"].is_number() {
-                    puzzle.vertex_stones.insert(Pos::new(x as i8, y as i8));
+                    puzzle
+                        .vertex_stones
+                        .insert(Pos::new(x as Coord, y as Coord));
                 }
             }
         }
 
         // Process edges (stone, broken edge)
+        let no_edge = json::JsonValue::Null;
         for x in 0..=width {
             for y in 0..=height {
-                let up = &data["grid"][x * 2][(height - y) * 2 - 1];
+                // Nothing goes up from the top row
+                let up = match height - y {
+                    0 => &no_edge,
+                    row => &data["grid"][x * 2][row * 2 - 1],
+                };
                 let right = &data["grid"][x * 2 + 1][(height - y) * 2];
 
                 if up["dot"].is_number() {
                     puzzle
                         .edge_stones
-                        .insert(EdgePos::new(x as i8, y as i8, Direction::Up));
+                        .insert(EdgePos::new(x as Coord, y as Coord, Direction::Up));
                 }
                 if right["dot"].is_number() {
-                    puzzle
-                        .edge_stones
-                        .insert(EdgePos::new(x as i8, y as i8, Direction::Right));
+                    puzzle.edge_stones.insert(EdgePos::new(
+                        x as Coord,
+                        y as Coord,
+                        Direction::Right,
+                    ));
                 }
                 if up["gap"].is_number() {
-                    puzzle
-                        .blocked_edges
-                        .insert(EdgePos::new(x as i8, y as i8, Direction::Up));
+                    puzzle.blocked_edges.insert(EdgePos::new(
+                        x as Coord,
+                        y as Coord,
+                        Direction::Up,
+                    ));
                 }
                 if right["gap"].is_number() {
-                    puzzle
-                        .blocked_edges
-                        .insert(EdgePos::new(x as i8, y as i8, Direction::Right));
+                    puzzle.blocked_edges.insert(EdgePos::new(
+                        x as Coord,
+                        y as Coord,
+                        Direction::Right,
+                    ));
                 }
             }
         }
@@ -882,7 +1613,7 @@ impl Puzzle {
         for x in 0..width {
             for y in 0..height {
                 let cell = &data["grid"][x * 2 + 1][(height - y) * 2 - 1];
-                let pos = Pos::new(x as i8, y as i8);
+                let pos = Pos::new(x as Coord, y as Coord);
                 let nb_of_colors = colors.len();
 
                 if cell.is_null() {
@@ -948,6 +1679,14 @@ impl Puzzle {
             }
         }
 
+        puzzle.validate_structure().map_err(|errors| {
+            errors
+                .iter()
+                .map(PuzzleError::to_string)
+                .collect::<Vec<_>>()
+                .join(", ")
+        })?;
+
         Ok(puzzle)
     }
 }
@@ -1011,17 +1750,13 @@ mod tests {
 
     #[test]
     fn test_2x1_squares() {
-        // This puzzle is a 2x1 puzzle with the start in the middle
-        // at the bottom and the exit above it. The left cell contains
-        // a black square, the right one contains a white square
-        let puzzle = Puzzle {
-            width: 2,
-            height: 1,
-            starts: vec![Pos::new(1, 0)],
-            ends: vec![Pos::new(1, 1)],
-            squares: [(Pos::new(0, 0), 0), (Pos::new(1, 0), 1)].into(),
-            ..Default::default()
-        };
+        let puzzle: Puzzle = r"
++---E---+
+|[a]|[b]|
++---S---+
+"
+        .parse()
+        .unwrap();
         let solution = SolutionPath::new(Pos::new(1, 0), "U".into()).unwrap();
 
         assert!(puzzle.is_solution(&solution));
@@ -1029,16 +1764,13 @@ mod tests {
 
     #[test]
     fn test_2x1_stars() {
-        // This puzzle is a 2x1 puzzle with the start in the middle
-        // at the bottom and the exit above it. Both cells contain a star
-        let puzzle = Puzzle {
-            width: 2,
-            height: 1,
-            starts: vec![Pos::new(1, 0)],
-            ends: vec![Pos::new(1, 1)],
-            stars: [(Pos::new(0, 0), 0), (Pos::new(1, 0), 0)].into(),
-            ..Default::default()
-        };
+        let puzzle: Puzzle = r"
++---E---+
+|*a*|*a*|
++---S---+
+"
+        .parse()
+        .unwrap();
         let solutions = [
             SolutionPath::new(Pos::new(1, 0), "LUR".into()).unwrap(),
             SolutionPath::new(Pos::new(1, 0), "RUL".into()).unwrap(),
@@ -1051,21 +1783,372 @@ mod tests {
 
     #[test]
     fn test_triangles() {
+        let puzzle: Puzzle = r"
++---+---+---+---E
+|^^^| ^ |   |   |
++---+---+---+---+
+|   |   |^^ |^^ |
++---+---+---+---+
+|   |   |   |^^^|
++---+---+---+---+
+|   |   | ^ |   |
+S---+---+---+---+
+"
+        .parse()
+        .unwrap();
+
+        let solution =
+            SolutionPath::new(Pos::new(0, 0), "UURULURRRDLDDLDRRRULURUU".into()).unwrap();
+        assert!(puzzle.is_solution(&solution));
+    }
+
+    #[test]
+    fn test_validate_structure() {
+        assert_eq!(Puzzle::default().validate_structure(), Ok(()));
+
         let puzzle = Puzzle {
-            triangles: [
-                (Pos::new(0, 3), 3),
-                (Pos::new(1, 3), 1),
-                (Pos::new(2, 2), 2),
-                (Pos::new(3, 2), 2),
-                (Pos::new(3, 1), 3),
-                (Pos::new(2, 0), 1),
+            starts: vec![Pos::new(-1, 0)],
+            ends: vec![],
+            blocked_edges: [EdgePos::new(0, 0, Direction::Up)].into(),
+            outside_positions: [Pos::new(1, 0)].into(),
+            edge_stones: [
+                EdgePos::new(0, 1, Direction::Down),
+                EdgePos::new(2, 0, Direction::Right),
             ]
             .into(),
-            ..Puzzle::default_with_size(4, 4)
+            squares: [(Pos::new(0, 0), 0), (Pos::new(1, 0), 0)].into(),
+            stars: [(Pos::new(0, 0), 1)].into(),
+            triangles: [(Pos::new(0, 1), 4)].into(),
+            ..Puzzle::default_with_size(2, 2)
         };
 
-        let solution =
-            SolutionPath::new(Pos::new(0, 0), "UURULURRRDLDDLDRRRULURUU".into()).unwrap();
+        assert_eq!(
+            puzzle.validate_structure(),
+            Err(vec![
+                PuzzleError::NoEnd,
+                PuzzleError::StartOutOfBounds(Pos::new(-1, 0)),
+                PuzzleError::StoneOnBlockedEdge(EdgePos::new(0, 0, Direction::Up)),
+                PuzzleError::EdgeStoneOutOfBounds(EdgePos::new(2, 0, Direction::Right)),
+                PuzzleError::MultipleSymbols(Pos::new(0, 0)),
+                PuzzleError::SymbolOutside(Pos::new(1, 0)),
+                PuzzleError::InvalidTriangleCount(Pos::new(0, 1), 4),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_large_grid() {
+        let puzzle = Puzzle::default_with_size(300, 1);
+        let solution = SolutionPath::new(Pos::new(0, 0), "R".repeat(300) + "U").unwrap();
         assert!(puzzle.is_solution(&solution));
+
+        let too_large = Puzzle::default_with_size(Puzzle::MAX_SIZE + 1, 1);
+        assert_eq!(
+            too_large.validate_structure(),
+            Err(vec![PuzzleError::InvalidSize {
+                width: Puzzle::MAX_SIZE + 1,
+                height: 1
+            }])
+        );
+    }
+
+    #[test]
+    fn test_validate_squares() {
+        let puzzle: Puzzle = r"
++---E---+
+|[a]|[b]|
++---S---+
+"
+        .parse()
+        .unwrap();
+
+        let solution = SolutionPath::new(Pos::new(1, 0), "U".into()).unwrap();
+        assert_eq!(puzzle.validate(&solution), Ok(()));
+
+        let wrong = SolutionPath::new(Pos::new(1, 0), "RUL".into()).unwrap();
+        assert_eq!(
+            puzzle.validate(&wrong),
+            Err(vec![Violation {
+                kind: ViolationKind::Cell(Pos::new(1, 0), CellType::Square(1)),
+                area: Some(0),
+                cancels_used: vec![],
+            }])
+        );
+    }
+
+    #[test]
+    fn test_validate_path() {
+        let puzzle = Puzzle::default();
+        let path: SolutionPath = vec![Pos::new(1, 0), Pos::new(1, 1), Pos::new(0, 0)].into();
+
+        let kinds: Vec<_> = puzzle
+            .validate(&path)
+            .unwrap_err()
+            .into_iter()
+            .map(|v| v.kind)
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                ViolationKind::Path(PathError::NotAStart(Pos::new(1, 0))),
+                ViolationKind::Path(PathError::NotAdjacent(Pos::new(1, 1), Pos::new(0, 0))),
+                ViolationKind::Path(PathError::NotAnEnd(Pos::new(0, 0))),
+            ]
+        );
+
+        // The areas are only checked once the path can be drawn
+        let mut puzzle = Puzzle::default();
+        puzzle.stars.insert(Pos::new(0, 0), 0);
+        let unfinished = SolutionPath::new(Pos::new(0, 0), "U".into()).unwrap();
+        assert_eq!(
+            puzzle.validate(&unfinished),
+            Err(vec![Violation {
+                kind: ViolationKind::Path(PathError::NotAnEnd(Pos::new(0, 1))),
+                area: None,
+                cancels_used: vec![],
+            }])
+        );
+        let finished = SolutionPath::new(Pos::new(0, 0), "UR".into()).unwrap();
+        assert_eq!(
+            puzzle.validate(&finished),
+            Err(vec![Violation {
+                kind: ViolationKind::Cell(Pos::new(0, 0), CellType::Star(0)),
+                area: Some(0),
+                cancels_used: vec![],
+            }])
+        );
+    }
+
+    #[test]
+    fn test_path_from_str() {
+        for text in ["(0, 0) UURRDL", "(-1, 12) ", "Empty solution"] {
+            let path: SolutionPath = text.parse().unwrap();
+            assert_eq!(path.to_string(), text);
+        }
+
+        let path: SolutionPath = "(3, 1) LU".parse().unwrap();
+        assert_eq!(
+            path,
+            vec![Pos::new(3, 1), Pos::new(2, 1), Pos::new(2, 2)].into()
+        );
+
+        for text in [
+            "",
+            "(0, 0)",
+            "(0,0) U",
+            " (0, 0) U",
+            "(+1, 0) U",
+            "(01, 0) U",
+            "0, 0 U",
+        ] {
+            assert_eq!(
+                text.parse::<SolutionPath>(),
+                Err(PathError::Malformed(text.to_string()))
+            );
+        }
+        assert_eq!(
+            "(0, 0) UR ".parse::<SolutionPath>(),
+            Err(PathError::InvalidDirection(' '))
+        );
+        assert_eq!(
+            "(32767, 0) UR".parse::<SolutionPath>(),
+            Err(PathError::Overflow(Pos::new(32767, 1), Direction::Right))
+        );
+    }
+
+    #[test]
+    fn test_path_errors() {
+        let puzzle: Puzzle = r"
++---+---E
+|   |   |
++---+---+
+|   |   |
+S---+   +
+"
+        .parse()
+        .unwrap();
+        let check = |start, dirs: &str| {
+            SolutionPath::new(start, dirs.into())
+                .unwrap()
+                .validate_against(&puzzle)
+        };
+
+        assert_eq!(
+            SolutionPath::new(Pos::new(0, 0), "UX".into()),
+            Err(PathError::InvalidDirection('X'))
+        );
+        assert_eq!(
+            SolutionPath::from(vec![Pos::new(0, 0), Pos::new(1, 1)]).try_as_edge_path(),
+            Err(PathError::NotAdjacent(Pos::new(0, 0), Pos::new(1, 1)))
+        );
+        assert_eq!(
+            SolutionPath::from(vec![]).validate_against(&puzzle),
+            Err(PathError::Empty)
+        );
+        assert_eq!(check(Pos::new(0, 0), "UURR"), Ok(()));
+        assert_eq!(
+            check(Pos::new(1, 0), "UURR"),
+            Err(PathError::NotAStart(Pos::new(1, 0)))
+        );
+        assert_eq!(
+            check(Pos::new(0, 0), "UUR"),
+            Err(PathError::NotAnEnd(Pos::new(1, 2)))
+        );
+        assert_eq!(
+            SolutionPath::new(Pos::new(0, 0), "UUR".into())
+                .unwrap()
+                .validate_prefix_against(&puzzle),
+            Ok(())
+        );
+        assert_eq!(
+            check(Pos::new(0, 0), "LUUURRR"),
+            Err(PathError::OutOfBounds(Pos::new(-1, 0)))
+        );
+        assert_eq!(
+            check(Pos::new(0, 0), "URDLUURR"),
+            Err(PathError::SelfIntersection(Pos::new(0, 0)))
+        );
+        assert_eq!(
+            check(Pos::new(0, 0), "RRUU"),
+            Err(PathError::BlockedEdge(EdgePos::new(1, 0, Direction::Right)))
+        );
+    }
+
+    #[test]
+    fn test_validate_cancels() {
+        // The canceller is alone with the stone, but nothing cancels the star
+        let puzzle: Puzzle = r"
+o---+---+---E
+|\a/|   |*b*|
+S---+---+---+
+"
+        .parse()
+        .unwrap();
+        let solution = SolutionPath::new(Pos::new(0, 0), "RURR".into()).unwrap();
+
+        assert_eq!(
+            puzzle.validate(&solution),
+            Err(vec![Violation {
+                kind: ViolationKind::Cell(Pos::new(2, 0), CellType::Star(1)),
+                area: Some(1),
+                cancels_used: vec![],
+            }])
+        );
+
+        // Now the canceller is in the same area as the star and the stone
+        let solution = SolutionPath::new(Pos::new(0, 0), "RRRU".into()).unwrap();
+        assert_eq!(
+            puzzle.validate(&solution),
+            Err(vec![Violation {
+                kind: ViolationKind::Cell(Pos::new(2, 0), CellType::Star(1)),
+                area: Some(0),
+                cancels_used: vec![Pos::new(0, 0)],
+            }])
+        );
+    }
+
+    #[test]
+    fn test_group_by_regions() {
+        let puzzle: Puzzle = r"
++---+---E
+|   |   |
+S---+---+
+"
+        .parse()
+        .unwrap();
+        let path = |dirs: &str| SolutionPath::new(Pos::new(0, 0), dirs.into()).unwrap();
+        let paths = ["URR", "URDRU", "RRU", "RUR"].map(path);
+
+        assert_eq!(
+            puzzle.group_by_regions(&paths),
+            Ok(vec![
+                RegionClass {
+                    representative: path("URR"),
+                    size: 2,
+                },
+                RegionClass {
+                    representative: path("RUR"),
+                    size: 2,
+                },
+            ])
+        );
+
+        // Paths with steps between vertices that are not neighbours
+        let jump: SolutionPath = vec![Pos::new(0, 0), Pos::new(2, 0), Pos::new(2, 1)].into();
+        let hole = PathError::NotAdjacent(Pos::new(0, 0), Pos::new(2, 0));
+        assert_eq!(puzzle.areas(&jump).err(), Some(hole.clone()));
+        assert_eq!(
+            puzzle.floodfill(Pos::new(0, 0), &jump).err(),
+            Some(hole.clone())
+        );
+        assert_eq!(puzzle.group_by_regions(&[path("URR"), jump]), Err(hole));
+        assert_eq!(
+            puzzle.partition(&path("UR")),
+            Err(PathError::NotAnEnd(Pos::new(1, 1)))
+        );
+    }
+
+    #[test]
+    fn test_polys_left_with_ylops() {
+        // The first poly covers the whole area, the other one can't be placed
+        let mut puzzle = Puzzle::default_with_size(3, 1);
+        puzzle.starts = vec![Pos::new(0, 0)];
+        let line = Poly {
+            rotatable: false,
+            minos: vec![Pos::new(0, 0), Pos::new(1, 0), Pos::new(2, 0)],
+        };
+        puzzle.polys.insert(Pos::new(0, 0), line.clone());
+        puzzle.polys.insert(Pos::new(1, 0), line.clone());
+        puzzle.ylops.insert(Pos::new(2, 0), line.clone());
+
+        let path = SolutionPath::new(Pos::new(0, 0), "RRRU".into()).unwrap();
+        assert!(!puzzle.is_solution(&path));
+        let kinds: Vec<_> = puzzle
+            .validate(&path)
+            .unwrap_err()
+            .into_iter()
+            .map(|v| v.kind)
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                ViolationKind::Cell(Pos::new(0, 0), CellType::Poly(line.clone())),
+                ViolationKind::Cell(Pos::new(1, 0), CellType::Poly(line.clone())),
+                ViolationKind::Cell(Pos::new(2, 0), CellType::Ylop(line)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_stones_outside_areas() {
+        // The only cell is outside, so no area can check the stones
+        let puzzle: Puzzle = r"
++---E
+|///o
+S---o
+"
+        .parse()
+        .unwrap();
+
+        let path = SolutionPath::new(Pos::new(0, 0), "RU".into()).unwrap();
+        assert!(puzzle.is_solution(&path));
+
+        let path = SolutionPath::new(Pos::new(0, 0), "UR".into()).unwrap();
+        assert!(!puzzle.is_solution(&path));
+        assert_eq!(
+            puzzle.validate(&path),
+            Err(vec![
+                Violation {
+                    kind: ViolationKind::VertexStone(Pos::new(1, 0)),
+                    area: None,
+                    cancels_used: vec![],
+                },
+                Violation {
+                    kind: ViolationKind::EdgeStone(EdgePos::new(1, 0, Direction::Up)),
+                    area: None,
+                    cancels_used: vec![],
+                },
+            ])
+        );
     }
 }
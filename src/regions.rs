@@ -0,0 +1,181 @@
+use crate::{bitset::*, puzzle::*};
+
+/// Partition of the cells of a puzzle into areas, kept up to date
+/// as edges are added to the solution path.
+///
+/// Since the path can only grow, areas are only ever split in two.
+/// This happens when the new edge closes a loop with the walls of the
+/// puzzle, which requires the path to touch a wall.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct Regions {
+    /// Region of every cell, `OUTSIDE` for cells outside of the puzzle
+    labels: Box<[u32]>,
+    /// Label to give to the next region that gets split off
+    next_label: u32,
+    width: Coord,
+    height: Coord,
+}
+
+impl Regions {
+    pub const OUTSIDE: u32 = u32::MAX;
+
+    /// Computes the areas of the puzzle when there is no path yet.
+    /// There may be several of them if `outside_positions` cut the puzzle.
+    pub fn new(puzzle: &Puzzle) -> Self {
+        // Cells start out as OUTSIDE, and get their label once they are reached
+        let mut res = Self {
+            labels: vec![Self::OUTSIDE; Cells::capacity(puzzle.width, puzzle.height)].into(),
+            next_label: 0,
+            width: puzzle.width,
+            height: puzzle.height,
+        };
+
+        let no_edges = EdgeSet::new(puzzle);
+        for x in 0..puzzle.width {
+            for y in 0..puzzle.height {
+                let pos = Pos::new(x, y);
+                if puzzle.contains_cell(&pos) && res.label(&pos) == Some(Self::OUTSIDE) {
+                    let label = res.next_label;
+                    res.next_label += 1;
+                    for cell in res.connected_cells(puzzle, &no_edges, pos, None) {
+                        res.labels[res.index(&cell)] = label;
+                    }
+                }
+            }
+        }
+
+        res
+    }
+
+    #[inline(always)]
+    fn index(&self, cell: &Pos) -> usize {
+        Cells::index(cell, self.width, self.height).unwrap()
+    }
+
+    /// Region of the cell, `None` if the cell is not in the grid
+    #[inline(always)]
+    pub fn label(&self, cell: &Pos) -> Option<u32> {
+        Cells::index(cell, self.width, self.height).map(|idx| self.labels[idx])
+    }
+
+    /// Number of bytes allocated for the labels
+    pub fn heap_size(&self) -> usize {
+        std::mem::size_of_val(&*self.labels)
+    }
+
+    /// Updates the regions after `edge` was added to `path_edges`, and
+    /// returns the labels of both sides if the edge split a region in two.
+    ///
+    /// `head_on_wall` must be true if the vertex reached by the edge
+    /// touches a cell outside of the puzzle, otherwise no split is possible.
+    pub fn add_edge(
+        &mut self,
+        puzzle: &Puzzle,
+        path_edges: &EdgeSet,
+        edge: &EdgePos,
+        head_on_wall: bool,
+    ) -> Option<(u32, u32)> {
+        if !head_on_wall {
+            return None;
+        }
+
+        let (left, right) = edge.get_neighbouring_cells();
+        let label = self.label(&left)?;
+        if label == Self::OUTSIDE || self.label(&right) != Some(label) {
+            return None;
+        }
+
+        // Look for a way around the new edge
+        let left_side = self.connected_cells(puzzle, path_edges, left, Some(right));
+        if left_side.last() == Some(&right) {
+            return None;
+        }
+
+        let new_label = self.next_label;
+        self.next_label += 1;
+        for cell in left_side {
+            let idx = self.index(&cell);
+            self.labels[idx] = new_label;
+        }
+
+        Some((new_label, label))
+    }
+
+    /// Returns the cells of the region of `start` that can be reached without
+    /// crossing the path. Stops early if `target` is reached, in which case
+    /// it is the last cell returned.
+    fn connected_cells(
+        &self,
+        puzzle: &Puzzle,
+        path_edges: &EdgeSet,
+        start: Pos,
+        target: Option<Pos>,
+    ) -> Vec<Pos> {
+        let label = self.label(&start);
+        let mut visited = CellSet::new(puzzle);
+        let mut res = vec![];
+        let mut to_visit = vec![start];
+
+        while let Some(pos) = to_visit.pop() {
+            if !puzzle.contains_cell(&pos) || self.label(&pos) != label || !visited.insert(&pos) {
+                continue;
+            }
+
+            res.push(pos);
+            if Some(pos) == target {
+                return res;
+            }
+
+            for dir in Direction::VARIANTS {
+                if !path_edges.contains(&pos.get_cell_edge_in_direction(dir)) {
+                    to_visit.push(pos.move_direction(dir));
+                }
+            }
+        }
+
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_outside_positions() {
+        let puzzle = Puzzle {
+            outside_positions: [Pos::new(1, 0), Pos::new(1, 1)].into(),
+            ..Puzzle::default_with_size(3, 2)
+        };
+        let regions = Regions::new(&puzzle);
+
+        assert_eq!(regions.label(&Pos::new(0, 0)), Some(0));
+        assert_eq!(regions.label(&Pos::new(0, 1)), Some(0));
+        assert_eq!(regions.label(&Pos::new(1, 0)), Some(Regions::OUTSIDE));
+        assert_eq!(regions.label(&Pos::new(2, 1)), Some(1));
+        assert_eq!(regions.label(&Pos::new(2, 0)), Some(1));
+        assert_eq!(regions.label(&Pos::new(3, 0)), None);
+    }
+
+    #[test]
+    fn test_split() {
+        // Draw a vertical line through the middle of a 2x2 puzzle
+        let puzzle = Puzzle::default_with_size(2, 2);
+        let mut regions = Regions::new(&puzzle);
+        let mut path_edges = EdgeSet::new(&puzzle);
+
+        let first = EdgePos::new(1, 0, Direction::Up);
+        path_edges.insert(&first);
+        assert_eq!(regions.add_edge(&puzzle, &path_edges, &first, false), None);
+
+        let second = EdgePos::new(1, 1, Direction::Up);
+        path_edges.insert(&second);
+        assert_eq!(
+            regions.add_edge(&puzzle, &path_edges, &second, true),
+            Some((1, 0))
+        );
+        let labels =
+            [(0, 0), (0, 1), (1, 0), (1, 1)].map(|(x, y)| regions.label(&Pos::new(x, y)).unwrap());
+        assert_eq!(labels, [1, 1, 0, 0]);
+    }
+}
@@ -1,42 +1,136 @@
 #![allow(unused)]
 
-use std::collections::{HashSet, VecDeque};
+use std::{
+    collections::{hash_map::Entry, HashMap, HashSet, VecDeque},
+    fmt::Display,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use crate::{bitset::*, puzzle::*, regions::Regions};
+
+/// Vertex of a candidate path, stored in the solver's arena.
+/// Following the parents back to a start gives the whole path.
+#[derive(Clone, Copy)]
+struct PathNode {
+    /// Index of the previous vertex in the arena, `NO_PARENT` for starts
+    parent: u32,
+    /// Vertex reached by the last move
+    pos: Pos,
+}
+
+const NO_PARENT: u32 = u32::MAX;
+
+/// Number of vertices the arena can hold before their indices run into `NO_PARENT`,
+/// keeping room for the children of one state
+const MAX_ARENA_SIZE: usize = NO_PARENT as usize - Direction::VARIANTS.len();
 
-use crate::puzzle::*;
+/// Size of the arena above which the vertices of discarded paths are dropped.
+/// Tests use a small one so that every search goes through it.
+const MIN_ARENA_COMPACTION: usize = if cfg!(test) { 64 } else { 1 << 20 };
 
 #[derive(Clone)]
 pub struct PartialSolution {
-    /// Candidate solution path
-    path: SolutionPath,
-    partial_area_left: HashSet<Pos>,
-    partial_area_right: HashSet<Pos>,
-    // /// Completed areas that have already been checked and are correct
-    // full_areas: Vec<HashSet<Pos>>,
+    /// Index of the last vertex of the candidate path in the arena
+    node: u32,
+    /// Number of vertices in the candidate path
+    len: usize,
+    /// Last vertex of the path
+    head: Pos,
+    /// Vertex before the last one, if any
+    prev: Option<Pos>,
+    /// Vertices of the path, for quick self-intersection checks
+    visited: VertexSet,
+    /// Edges of the path
+    edges: EdgeSet,
+    /// Current areas, split up as the path encloses them
+    regions: Regions,
+    /// Cells of the areas that the path can't reach anymore,
+    /// and that have already been checked and are correct
+    sealed: CellSet,
+    partial_area_left: CellSet,
+    partial_area_right: CellSet,
     // /// Cancels that are not yet taken by partial or full areas
     // cancels_left: u8,
     /// Number of reachable ends left
     reachable_ends: u8,
+    /// Number of equivalent candidate paths merged into this one
+    multiplicity: u128,
 }
 
 impl PartialSolution {
-    pub fn new(start: Pos, cancels: u8, ends: u8) -> Self {
+    fn new(
+        puzzle: &Puzzle,
+        regions: &Regions,
+        node: u32,
+        start: Pos,
+        cancels: u8,
+        ends: u8,
+    ) -> Self {
+        let mut visited = VertexSet::new(puzzle);
+        visited.insert(&start);
+
         Self {
-            path: vec![start].into(),
-            partial_area_left: HashSet::new(),
-            partial_area_right: HashSet::new(),
-            // full_areas: vec![],
+            node,
+            len: 1,
+            head: start,
+            prev: None,
+            visited,
+            edges: EdgeSet::new(puzzle),
+            regions: regions.clone(),
+            sealed: CellSet::new(puzzle),
+            partial_area_left: CellSet::new(puzzle),
+            partial_area_right: CellSet::new(puzzle),
             // cancels_left: cancels,
             reachable_ends: ends,
+            multiplicity: 1,
         }
     }
 }
 
+/// Part of a partial solution that determines all of its possible endings.
+/// Two candidate paths with the same key have the same number of valid endings,
+/// which is all that matters since the pruning never rejects a valid ending.
+#[derive(PartialEq, Eq, Hash)]
+struct TranspositionKey {
+    head: Pos,
+    /// Vertices that the rest of the path can go through
+    reachable: VertexSet,
+    /// Vertices of the path that can still matter for the constraints
+    visited: VertexSet,
+    /// Edges of the path that can still matter for the constraints
+    edges: EdgeSet,
+    sealed: CellSet,
+}
+
 pub struct BFSSolverConfig {
     pub simple_end_reachability_check: bool,
     pub edge_stones: bool,
     pub partial_area_check: bool,
     pub closed_area_check: bool,
+    /// When counting, merge candidate paths that have the same possible endings
+    pub merge_transpositions: bool,
+    /// In `check_uniqueness`, treat solutions that split the puzzle into the same areas as one
+    pub uniqueness_by_regions: bool,
     pub max_solutions: u32, // if 0, get all solutions
+
+    // Resource limits
+    /// Wall-clock time after which the search stops
+    pub time_limit: Option<Duration>,
+    pub max_states: u64,       // if 0, no limit
+    pub max_queue_size: usize, // if 0, no limit
+    /// Estimated memory used by the search, in bytes. If 0, no limit
+    pub max_memory: usize,
+    /// Flag that stops the search when it is set from another thread
+    pub cancel: Option<Arc<AtomicBool>>,
+
+    /// Called regularly during the search to report how it is going
+    pub progress: Option<ProgressCallback>,
+    /// Minimum time between two progress reports
+    pub progress_interval: Duration,
 }
 
 impl Default for BFSSolverConfig {
@@ -45,160 +139,981 @@ impl Default for BFSSolverConfig {
             simple_end_reachability_check: true,
             edge_stones: true,
             partial_area_check: true,
-            closed_area_check: false, // TODO: change this when it gets implemented
+            closed_area_check: true,
+            merge_transpositions: true,
+            uniqueness_by_regions: false,
             max_solutions: 0,
+            time_limit: None,
+            max_states: 0,
+            max_queue_size: 70_000_000,
+            max_memory: 0,
+            cancel: None,
+            progress: None,
+            progress_interval: Duration::from_millis(500),
+        }
+    }
+}
+
+pub type ProgressCallback = Box<dyn FnMut(&Progress)>;
+
+/// Snapshot of a running search, see [`BFSSolverConfig::progress`]
+#[derive(Clone, Debug)]
+pub struct Progress {
+    pub elapsed: Duration,
+    pub queue_size: usize,
+    /// Number of moves of the candidates being searched
+    pub path_len: usize,
+    pub solutions: u128,
+    pub stats: SolverStats,
+}
+
+/// Counters describing the work done by the solver
+#[derive(Clone, Debug, Default)]
+pub struct SolverStats {
+    pub states_visited: u64,
+    /// Number of states that were merged into an equivalent one
+    pub states_merged: u64,
+
+    // Candidates rejected by each pruning rule
+    pub pruned_edge_stones: u64,
+    pub pruned_partial_areas: u64,
+    pub pruned_end_reachability: u64,
+    pub pruned_closed_areas: u64,
+
+    /// Number of areas that went through the complete validation
+    pub full_validations: u64,
+    /// Total time spent in complete validations
+    pub validation_time: Duration,
+
+    pub peak_queue_size: usize,
+    /// Highest estimated memory used by the queue and arena, in bytes
+    pub peak_memory: usize,
+}
+
+impl SolverStats {
+    /// Number of candidates rejected by all the pruning rules
+    pub fn pruned(&self) -> u64 {
+        self.pruned_edge_stones
+            + self.pruned_partial_areas
+            + self.pruned_end_reachability
+            + self.pruned_closed_areas
+    }
+}
+
+impl Display for SolverStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let validations = format!("{} in {:?}", self.full_validations, self.validation_time);
+        let memory = format!("{} KiB", self.peak_memory / 1024);
+        let lines: [(&str, &dyn Display); 9] = [
+            ("States visited", &self.states_visited),
+            ("States merged", &self.states_merged),
+            ("Pruned by edge stones", &self.pruned_edge_stones),
+            ("Pruned by partial areas", &self.pruned_partial_areas),
+            ("Pruned by end reachability", &self.pruned_end_reachability),
+            ("Pruned by closed areas", &self.pruned_closed_areas),
+            ("Full validations", &validations),
+            ("Peak queue size", &self.peak_queue_size),
+            ("Peak memory", &memory),
+        ];
+
+        for (idx, (name, value)) in lines.iter().enumerate() {
+            if idx > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{:<28}{}", format!("{name}:"), value)?;
+        }
+        Ok(())
+    }
+}
+
+/// Limit of the config that stopped a search
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Limit {
+    Solutions,
+    Time,
+    States,
+    QueueSize,
+    /// Too much memory was used, or the arena ran out of indices
+    Memory,
+}
+
+/// How the last search ended
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SearchOutcome {
+    /// Every candidate was searched, the results are complete
+    Finished,
+    /// The search stopped early, the results are partial
+    LimitReached(Limit),
+    /// The search was stopped through the cancel flag, the results are partial
+    Cancelled,
+}
+
+impl Display for SearchOutcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SearchOutcome::Finished => write!(f, "finished"),
+            SearchOutcome::LimitReached(Limit::Solutions) => write!(f, "solution limit"),
+            SearchOutcome::LimitReached(Limit::Time) => write!(f, "time limit"),
+            SearchOutcome::LimitReached(Limit::States) => write!(f, "state limit"),
+            SearchOutcome::LimitReached(Limit::QueueSize) => write!(f, "queue size limit"),
+            SearchOutcome::LimitReached(Limit::Memory) => write!(f, "memory limit"),
+            SearchOutcome::Cancelled => write!(f, "cancelled"),
         }
     }
 }
 
+/// Solutions found by a search, and how it ended
+#[derive(Clone, Debug, PartialEq)]
+pub struct SearchResult {
+    /// Solutions found, sorted by length. Empty when only counting.
+    pub solutions: Vec<SolutionPath>,
+    /// Number of solutions found
+    pub count: u128,
+    /// Whether the search finished, hit a limit, or was cancelled
+    pub outcome: SearchOutcome,
+}
+
+impl SearchResult {
+    /// Whether every candidate was searched, so the results are complete
+    pub fn is_complete(&self) -> bool {
+        self.outcome == SearchOutcome::Finished
+    }
+}
+
+/// Number of solutions of a puzzle, as found by [`BFSSolver::check_uniqueness`]
+#[derive(Clone, Debug, PartialEq)]
+pub enum Uniqueness {
+    Unsolvable,
+    Unique(SolutionPath),
+    /// The first two solutions found
+    Multiple(SolutionPath, SolutionPath),
+    /// A limit stopped the search before it could tell, with the solution
+    /// found so far if there is one
+    Incomplete(Option<SolutionPath>, SearchOutcome),
+}
+
+/// Which solutions the search records
+#[derive(Clone, Debug, PartialEq)]
+enum SolutionFilter {
+    All,
+    /// Only the solutions that split the puzzle into other areas than the first
+    /// one, whose partition is unset until it is found
+    NewPartition(Option<Vec<Option<usize>>>),
+}
+
+/// Moves that can continue a partial path, as found by [`BFSSolver::hint`]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Hint {
+    /// Next moves from the head of the path that lead to a solution
+    pub moves: Vec<Direction>,
+    /// Next moves that a limit stopped from being searched to the end
+    pub unknown: Vec<Direction>,
+    /// Whether the path is already a solution
+    pub complete: bool,
+}
+
+impl Hint {
+    /// Whether the path is not a solution, and no move from its head can lead to one
+    pub fn is_dead_end(&self) -> bool {
+        !self.complete && self.moves.is_empty() && self.unknown.is_empty()
+    }
+}
+
 pub struct BFSSolver {
     /// Initial puzzle
     puzzle: Puzzle,
+    /// Areas of the puzzle before any path is drawn
+    initial_regions: Regions,
+    /// Vertices that touch a cell outside of the puzzle
+    wall_vertices: VertexSet,
     /// Queue of potential solutions, sorted from shortest to longest
     queue: VecDeque<PartialSolution>,
+    /// Vertices of every path that was queued, linked to their parents
+    arena: Vec<PathNode>,
     /// List of solutions found so far
     solutions: Vec<SolutionPath>,
-
-    // Statistics
-    pub states_visited: u64,
+    /// Only count solutions instead of collecting them
+    count_only: bool,
+    /// Number of solutions found so far
+    solution_count: u128,
+    /// Solutions that are recorded, see [`BFSSolverConfig::uniqueness_by_regions`]
+    solution_filter: SolutionFilter,
+    /// Queue index of the states of the last level pushed, by transposition key.
+    /// Indices count from the start of the search, not from the queue front.
+    transpositions: HashMap<TranspositionKey, usize>,
+    /// Path length of the states in `transpositions`
+    transpositions_len: usize,
+    /// Number of states popped from the queue so far
+    queue_offset: usize,
+    /// Size of the arena at which it is compacted next
+    next_compaction: usize,
+
+    /// How the last search ended
+    outcome: SearchOutcome,
+
+    /// Work done by the last call to one of the search methods
+    pub stats: SolverStats,
 
     // Config
     pub config: BFSSolverConfig,
 }
 
 impl BFSSolver {
-    pub fn new(puzzle: &Puzzle) -> Self {
-        Self {
+    /// Creates a solver for the puzzle, or returns the structural problems
+    /// that would prevent it from being solved
+    pub fn new(puzzle: &Puzzle) -> Result<Self, Vec<PuzzleError>> {
+        puzzle.validate_structure()?;
+
+        let mut wall_vertices = VertexSet::new(puzzle);
+        for x in 0..=puzzle.width {
+            for y in 0..=puzzle.height {
+                let cells = [(x - 1, y - 1), (x - 1, y), (x, y - 1), (x, y)];
+                if cells
+                    .iter()
+                    .any(|&(x, y)| !puzzle.contains_cell(&Pos::new(x, y)))
+                {
+                    wall_vertices.insert(&Pos::new(x, y));
+                }
+            }
+        }
+
+        Ok(Self {
             puzzle: puzzle.clone(),
+            initial_regions: Regions::new(puzzle),
+            wall_vertices,
             queue: VecDeque::new(),
+            arena: vec![],
             solutions: vec![],
-            states_visited: 0,
+            count_only: false,
+            solution_count: 0,
+            solution_filter: SolutionFilter::All,
+            transpositions: HashMap::new(),
+            transpositions_len: 0,
+            queue_offset: 0,
+            next_compaction: MIN_ARENA_COMPACTION,
+            outcome: SearchOutcome::Finished,
+            stats: Default::default(),
             config: Default::default(),
+        })
+    }
+
+    /// Finds the solutions of the puzzle
+    pub fn solve(&mut self) -> SearchResult {
+        self.stats = SolverStats::default();
+        self.count_only = false;
+        self.search(None, Instant::now());
+        self.take_result()
+    }
+
+    /// Returns the number of solutions, without keeping them.
+    ///
+    /// The search runs in count mode: paths are not recorded in the arena and
+    /// solutions are not kept, so memory only grows with the queue. This also
+    /// allows merging equivalent candidates, see [`BFSSolverConfig::merge_transpositions`].
+    pub fn count(&mut self) -> SearchResult {
+        self.stats = SolverStats::default();
+        self.count_only = true;
+        self.search(None, Instant::now());
+        self.take_result()
+    }
+
+    /// Returns the solutions that begin with `prefix`, including the prefix itself
+    /// if it is one. The search starts at the head of the prefix, with the areas
+    /// and reachable ends left by its moves.
+    pub fn solve_from(&mut self, prefix: &SolutionPath) -> Result<SearchResult, PathError> {
+        prefix.validate_prefix_against(&self.puzzle)?;
+
+        self.stats = SolverStats::default();
+        self.count_only = false;
+        self.search(Some(prefix), Instant::now());
+        Ok(self.take_result())
+    }
+
+    /// Same as [`BFSSolver::solve_from`], only counting the solutions
+    pub fn count_from(&mut self, prefix: &SolutionPath) -> Result<SearchResult, PathError> {
+        prefix.validate_prefix_against(&self.puzzle)?;
+
+        self.stats = SolverStats::default();
+        self.count_only = true;
+        self.search(Some(prefix), Instant::now());
+        Ok(self.take_result())
+    }
+
+    /// Result of the last search, leaving no solutions behind
+    fn take_result(&mut self) -> SearchResult {
+        SearchResult {
+            solutions: std::mem::take(&mut self.solutions),
+            count: self.solution_count,
+            outcome: self.outcome,
         }
     }
 
-    pub fn solve(&mut self) -> Vec<SolutionPath> {
-        if !self.solutions.is_empty() {
-            return self.solutions.clone();
+    /// Tells whether the puzzle has no solution, exactly one, or more, stopping
+    /// at the second solution. With [`BFSSolverConfig::uniqueness_by_regions`],
+    /// solutions that split the puzzle into the same areas count as one.
+    pub fn check_uniqueness(&mut self) -> Uniqueness {
+        let max_solutions = std::mem::replace(&mut self.config.max_solutions, 2);
+        if self.config.uniqueness_by_regions {
+            self.solution_filter = SolutionFilter::NewPartition(None);
+        }
+
+        self.stats = SolverStats::default();
+        self.count_only = false;
+        self.search(None, Instant::now());
+        self.config.max_solutions = max_solutions;
+        self.solution_filter = SolutionFilter::All;
+
+        let mut solutions = std::mem::take(&mut self.solutions).into_iter();
+        let finished = matches!(
+            self.outcome,
+            SearchOutcome::Finished | SearchOutcome::LimitReached(Limit::Solutions)
+        );
+        match (solutions.next(), solutions.next()) {
+            (Some(first), Some(second)) => Uniqueness::Multiple(first, second),
+            (first, _) if !finished => Uniqueness::Incomplete(first, self.outcome),
+            (None, _) => Uniqueness::Unsolvable,
+            (Some(solution), None) => Uniqueness::Unique(solution),
+        }
+    }
+
+    /// Finds which moves from the head of a partial path can still be completed
+    /// into a solution. Each move is searched with the same pruning as
+    /// [`BFSSolver::solve`], until its first solution or one of the limits.
+    /// The time limit is shared by all the moves.
+    pub fn hint(&mut self, prefix: &SolutionPath) -> Result<Hint, PathError> {
+        prefix.validate_prefix_against(&self.puzzle)?;
+
+        let max_solutions = std::mem::replace(&mut self.config.max_solutions, 1);
+        self.stats = SolverStats::default();
+        self.count_only = true;
+        let start_time = Instant::now();
+
+        let mut hint = Hint {
+            complete: self.puzzle.is_solution(prefix),
+            ..Default::default()
+        };
+        let head = *prefix.last().unwrap();
+        for dir in Direction::VARIANTS {
+            let mut path = prefix.clone();
+            path.push(head.move_direction(dir));
+            if path.validate_prefix_against(&self.puzzle).is_err() {
+                continue;
+            }
+
+            self.search(Some(&path), start_time);
+            if self.solution_count > 0 {
+                hint.moves.push(dir);
+            } else if self.outcome != SearchOutcome::Finished {
+                hint.unknown.push(dir);
+            }
         }
 
+        self.config.max_solutions = max_solutions;
+        Ok(hint)
+    }
+
+    /// Searches the completions of `prefix`, or every path from the starts.
+    /// The prefix must already be validated. The time limit counts from `start_time`.
+    fn search(&mut self, prefix: Option<&SolutionPath>, start_time: Instant) {
+        self.queue.clear();
+        self.arena.clear();
+        self.solutions.clear();
+        self.solution_count = 0;
+        self.transpositions.clear();
+        self.queue_offset = 0;
+        self.next_compaction = MIN_ARENA_COMPACTION;
+
         // Init the search with the start nodes
-        for &start in self.puzzle.starts.iter() {
-            self.queue.push_back(PartialSolution::new(
-                start,
-                self.puzzle.cancels.len() as u8,
-                self.puzzle.ends.len() as u8,
-            ))
+        match prefix {
+            Some(prefix) => self.seed_prefix(prefix),
+            None => {
+                for start in self.puzzle.starts.clone() {
+                    let sol = self.start_solution(start);
+                    self.enqueue(sol, NO_PARENT);
+                }
+            }
         }
 
         // So long as there are states to be visited, keep processing them
+        self.outcome = SearchOutcome::Finished;
+        let mut states = 0;
+        let mut last_report = start_time;
         while !self.queue.is_empty() {
-            if self.queue.len() > 70_000_000 {
-                println!("Exiting here for fear of OOM");
-                return self.solutions.clone();
+            if self.arena.len() >= self.next_compaction {
+                self.compact_arena();
+                self.next_compaction = MIN_ARENA_COMPACTION.max(2 * self.arena.len());
             }
+
+            let memory = self.memory_usage();
+            self.stats.peak_memory = self.stats.peak_memory.max(memory);
+            self.stats.peak_queue_size = self.stats.peak_queue_size.max(self.queue.len());
+            if let Some(outcome) = self.check_limits(start_time, states, memory) {
+                self.outcome = outcome;
+                break;
+            }
+
+            if states.is_multiple_of(1024)
+                && self.config.progress.is_some()
+                && last_report.elapsed() >= self.config.progress_interval
+            {
+                last_report = Instant::now();
+                self.report_progress(start_time);
+            }
+
             let partial_sol = self.queue.pop_front().unwrap();
+            self.queue_offset += 1;
             self.process_partial_solution(partial_sol);
-            self.states_visited += 1;
+            self.stats.states_visited += 1;
+            states += 1;
 
             if self.config.max_solutions > 0
-                && self.solutions.len() >= self.config.max_solutions as usize
+                && self.solution_count >= self.config.max_solutions as u128
             {
-                return self.solutions.clone();
+                self.outcome = SearchOutcome::LimitReached(Limit::Solutions);
+                break;
             }
         }
 
-        self.solutions.clone()
+        self.report_progress(start_time);
     }
 
-    fn process_partial_solution(&mut self, sol: PartialSolution) {
-        let pos = sol.path.last().unwrap();
+    fn start_solution(&self, start: Pos) -> PartialSolution {
+        PartialSolution::new(
+            &self.puzzle,
+            &self.initial_regions,
+            NO_PARENT,
+            start,
+            self.puzzle.cancels.len() as u8,
+            self.puzzle.ends.len() as u8,
+        )
+    }
+
+    /// Queues the state of the search at the end of the prefix, replaying its moves
+    /// with the same pruning as the search. Nothing is queued if the pruning shows
+    /// that the prefix can't be completed. The prefix itself is counted as a
+    /// solution if it is one.
+    fn seed_prefix(&mut self, prefix: &SolutionPath) {
+        let mut sol = self.start_solution(prefix[0]);
+        let mut parent = NO_PARENT;
+        for window in prefix.windows(2) {
+            if !self.count_only {
+                parent = self.push_node(parent, window[0]);
+            }
+
+            let dir = window[0].get_direction_to(&window[1]).unwrap();
+            let Some(new_sol) = self.advance(&sol, dir) else {
+                return;
+            };
+            sol = new_sol;
+
+            if self.puzzle.ends.contains(&sol.head) {
+                sol.reachable_ends -= 1;
+            }
+        }
 
+        let ends_reached = self.puzzle.ends.contains(&sol.head);
+        if prefix.len() > 1 && ends_reached && self.regions_valid(&sol) {
+            self.record_solution(&sol, parent);
+        }
+
+        if self.config.simple_end_reachability_check && sol.reachable_ends == 0 {
+            self.stats.pruned_end_reachability += 1;
+            return;
+        }
+
+        self.enqueue(sol, parent);
+    }
+
+    /// Sends the current state of the search to the progress callback
+    fn report_progress(&mut self, start_time: Instant) {
+        let progress = Progress {
+            elapsed: start_time.elapsed(),
+            queue_size: self.queue.len(),
+            path_len: self.queue.front().map_or(0, |sol| sol.len),
+            solutions: self.solution_count,
+            stats: self.stats.clone(),
+        };
+
+        if let Some(report) = self.config.progress.as_mut() {
+            report(&progress);
+        }
+    }
+
+    /// Returns how the search should end if it went over one of the limits
+    /// of the config, after having visited `states` states
+    fn check_limits(
+        &self,
+        start_time: Instant,
+        states: u64,
+        memory: usize,
+    ) -> Option<SearchOutcome> {
+        let config = &self.config;
+        if config
+            .cancel
+            .as_ref()
+            .is_some_and(|cancel| cancel.load(Ordering::Relaxed))
+        {
+            return Some(SearchOutcome::Cancelled);
+        }
+
+        // Reading the clock is slower than the other checks, don't do it for every state
+        let out_of_time = states.is_multiple_of(1024)
+            && config
+                .time_limit
+                .is_some_and(|limit| start_time.elapsed() >= limit);
+
+        let limit = if config.max_states > 0 && states >= config.max_states {
+            Limit::States
+        } else if config.max_queue_size > 0 && self.queue.len() > config.max_queue_size {
+            Limit::QueueSize
+        } else if config.max_memory > 0 && memory > config.max_memory {
+            Limit::Memory
+        } else if self.arena.len() > MAX_ARENA_SIZE {
+            // The next state could push more vertices than there are indices left
+            Limit::Memory
+        } else if out_of_time {
+            Limit::Time
+        } else {
+            return None;
+        };
+
+        Some(SearchOutcome::LimitReached(limit))
+    }
+
+    /// Estimated number of bytes used by the queue and arena
+    fn memory_usage(&self) -> usize {
+        let state_size = self.queue.front().map_or(0, |sol| {
+            std::mem::size_of::<PartialSolution>()
+                + sol.visited.heap_size()
+                + sol.edges.heap_size()
+                + sol.regions.heap_size()
+                + sol.sealed.heap_size()
+                + sol.partial_area_left.heap_size()
+                + sol.partial_area_right.heap_size()
+        });
+
+        self.queue.len() * state_size + self.arena.len() * std::mem::size_of::<PathNode>()
+    }
+
+    /// Adds a vertex to the arena and returns its index.
+    /// `check_limits` stops the search before indices run out.
+    fn push_node(&mut self, parent: u32, pos: Pos) -> u32 {
+        let node = u32::try_from(self.arena.len())
+            .ok()
+            .filter(|&node| node != NO_PARENT)
+            .expect("too many vertices in the arena");
+        self.arena.push(PathNode { parent, pos });
+        node
+    }
+
+    /// Drops the vertices that no queued path goes through anymore
+    fn compact_arena(&mut self) {
+        let mut live = vec![false; self.arena.len()];
+        for sol in &self.queue {
+            let mut node = sol.node;
+            while node != NO_PARENT && !live[node as usize] {
+                live[node as usize] = true;
+                node = self.arena[node as usize].parent;
+            }
+        }
+
+        // Parents are always pushed before their children, so they are renumbered first
+        let mut new_index = vec![NO_PARENT; self.arena.len()];
+        let mut arena = Vec::with_capacity(live.iter().filter(|&&live| live).count());
+        for (idx, node) in self.arena.iter().enumerate() {
+            if live[idx] {
+                new_index[idx] = arena.len() as u32;
+                let parent = match node.parent {
+                    NO_PARENT => NO_PARENT,
+                    parent => new_index[parent as usize],
+                };
+                arena.push(PathNode {
+                    parent,
+                    pos: node.pos,
+                });
+            }
+        }
+
+        for sol in &mut self.queue {
+            sol.node = new_index[sol.node as usize];
+        }
+        self.arena = arena;
+    }
+
+    /// Rebuilds the path ending at the given node
+    fn path_to(&self, mut node: u32) -> SolutionPath {
+        let mut path = vec![];
+        while node != NO_PARENT {
+            let PathNode { parent, pos } = self.arena[node as usize];
+            path.push(pos);
+            node = parent;
+        }
+        path.reverse();
+
+        path.into()
+    }
+
+    fn process_partial_solution(&mut self, sol: PartialSolution) {
         for dir in Direction::VARIANTS {
-            let next = pos.move_direction(dir);
-
-            // Check illegal moves:
-            // - Check that the target vertex is in the puzzle
-            // - Check that the edge we want to take is not blocked
-            // - Check that the target vertex is not already part of the path
-            //   (in reverse because we are more likely to hit edges that closer
-            //   to the end of the candidate solution)
-            if !self.puzzle.contains_vertex(&next)
-                || self.puzzle.is_blocked(&EdgePos { pos: *pos, dir })
-                || sol.path.iter().rev().any(|p| p == &next)
-            {
+            let Some(mut new_sol) = self.advance(&sol, dir) else {
                 continue;
             };
 
-            let mut new_sol = sol.clone();
-            new_sol.path.push(next);
+            let next = new_sol.head;
+            if self.puzzle.ends.contains(&next) && self.regions_valid(&new_sol) {
+                self.record_solution(&new_sol, sol.node);
+            }
 
-            if self.config.edge_stones
-                && self.puzzle.cancels.is_empty()
-                && self.stones_invalid(&new_sol)
-            {
-                continue;
+            if self.puzzle.ends.contains(&next) {
+                new_sol.reachable_ends -= 1;
+                if self.config.simple_end_reachability_check && new_sol.reachable_ends == 0 {
+                    self.stats.pruned_end_reachability += 1;
+                    continue;
+                }
             }
 
-            let (left, right) = EdgePos { pos: *pos, dir }.get_neighbouring_cells();
+            self.enqueue(new_sol, sol.node);
+        }
+    }
+
+    /// Counts the candidate as a solution, and keeps its path unless only counting.
+    /// `parent` is the arena node of the vertex before its head.
+    fn record_solution(&mut self, sol: &PartialSolution, parent: u32) {
+        if !self.count_only {
+            let mut path = self.path_to(parent);
+            path.push(sol.head);
+
+            if let SolutionFilter::NewPartition(first) = &mut self.solution_filter {
+                // Solutions are always valid paths
+                let partition = self.puzzle.partition(&path).unwrap();
+                match first {
+                    Some(first) if *first == partition => return,
+                    Some(_) => {}
+                    None => *first = Some(partition),
+                }
+            }
+
+            self.solutions.push(path);
+        }
+
+        self.solution_count += sol.multiplicity;
+    }
+
+    /// Extends the candidate by one move. Returns `None` if the move is illegal,
+    /// or if one of the pruning rules shows that it can't lead to a solution.
+    fn advance(&mut self, sol: &PartialSolution, dir: Direction) -> Option<PartialSolution> {
+        let pos = &sol.head;
+        let next = pos.move_direction(dir);
+
+        // Check illegal moves:
+        // - Check that the target vertex is in the puzzle
+        // - Check that the edge we want to take is not blocked
+        // - Check that the target vertex is not already part of the path
+        if !self.puzzle.contains_vertex(&next)
+            || self.puzzle.is_blocked(&EdgePos { pos: *pos, dir })
+            || sol.visited.contains(&next)
+        {
+            return None;
+        };
+
+        if self.config.edge_stones
+            && self.puzzle.cancels.is_empty()
+            && self.stones_invalid(sol.prev, *pos, next)
+        {
+            self.stats.pruned_edge_stones += 1;
+            return None;
+        }
+
+        let edge = EdgePos { pos: *pos, dir };
+        let mut new_sol = sol.clone();
+        new_sol.len += 1;
+        new_sol.prev = Some(*pos);
+        new_sol.head = next;
+        new_sol.visited.insert(&next);
+        new_sol.edges.insert(&edge);
+
+        let head_on_wall = self.wall_vertices.contains(&next);
+        let split = new_sol
+            .regions
+            .add_edge(&self.puzzle, &new_sol.edges, &edge, head_on_wall);
+
+        // Check the areas that were just enclosed
+        // TODO: adapt this condition to cancels
+        if self.config.closed_area_check
+            && self.puzzle.cancels.is_empty()
+            && (split.is_some() || head_on_wall)
+            && !self.seal_closed_regions(&mut new_sol)
+        {
+            self.stats.pruned_closed_areas += 1;
+            return None;
+        }
+
+        let (left, right) = edge.get_neighbouring_cells();
+
+        // TODO: adapt this condition to cancels
+        if self.config.partial_area_check && self.puzzle.cancels.is_empty() {
+            // Compute & check partial areas
+            // When turning, the cells on the outer side are only connected
+            // through the corner cell, so that area ends if it is outside
+            if let Some(prev) = sol.prev {
+                let (prev_left, prev_right) = EdgePos {
+                    pos: prev,
+                    dir: prev.get_direction_to(pos).unwrap(),
+                }
+                .get_neighbouring_cells();
+                let corner = [(-1, -1), (-1, 0), (0, -1), (0, 0)]
+                    .map(|(dx, dy)| Pos::new(pos.x + dx, pos.y + dy))
+                    .into_iter()
+                    .find(|cell| ![prev_left, prev_right, left, right].contains(cell));
+                if corner.is_some_and(|corner| !self.puzzle.contains_cell(&corner)) {
+                    if prev_left != left {
+                        new_sol.partial_area_left.clear();
+                    }
+                    if prev_right != right {
+                        new_sol.partial_area_right.clear();
+                    }
+                }
+            }
+            // If left is outside, we are going along an edge and need to clear that area
+            if self.puzzle.contains_cell(&left) {
+                new_sol.partial_area_left.insert(&left);
+            } else {
+                new_sol.partial_area_left.clear();
+            }
+            // Same with right
+            if self.puzzle.contains_cell(&right) {
+                new_sol.partial_area_right.insert(&right);
+            } else {
+                new_sol.partial_area_right.clear();
+            }
 
-            // Check if we enclosed an area
-            if self.config.closed_area_check
-                && sol.path.len() > 2
-                && (!self.puzzle.contains_cell(&left) || !self.puzzle.contains_cell(&right))
+            // Discard the solution if one of the areas is invalid
+            if self.area_invalid(&new_sol.partial_area_left)
+                || self.area_invalid(&new_sol.partial_area_right)
             {
-                todo!("Enclosed area checking is not yet implemented")
+                self.stats.pruned_partial_areas += 1;
+                return None;
             }
+        }
 
-            // TODO: adapt this condition to cancels
-            if self.config.partial_area_check && self.puzzle.cancels.is_empty() {
-                // Compute & check partial areas
-                new_sol.partial_area_left.insert(left);
-                new_sol.partial_area_right.insert(right);
+        Some(new_sol)
+    }
 
-                // If left is outside, we are going along an edge and need to clear that area
-                if !self.puzzle.contains_cell(&left) {
-                    new_sol.partial_area_left.clear();
+    /// Adds the state to the queue, or merges it into an equivalent one
+    fn enqueue(&mut self, mut sol: PartialSolution, parent: u32) {
+        if self.count_only && self.config.merge_transpositions {
+            // The queue is sorted by length, so only states of the
+            // level currently being pushed can be merged into
+            if sol.len != self.transpositions_len {
+                self.transpositions.clear();
+                self.transpositions_len = sol.len;
+            }
+
+            match self.transpositions.entry(self.transposition_key(&sol)) {
+                Entry::Occupied(entry) => {
+                    let idx = entry.get() - self.queue_offset;
+                    self.queue[idx].multiplicity += sol.multiplicity;
+                    self.stats.states_merged += 1;
+                    return;
                 }
-                // Same with right
-                if !self.puzzle.contains_cell(&right) {
-                    new_sol.partial_area_right.clear();
+                Entry::Vacant(entry) => {
+                    entry.insert(self.queue_offset + self.queue.len());
                 }
+            }
+        }
 
-                // Discard the solution if one of the areas is invalid
-                if self.area_invalid(&new_sol.partial_area_left) {
-                    continue;
+        // Paths are only rebuilt to be returned, so counting doesn't need them
+        sol.node = if self.count_only {
+            NO_PARENT
+        } else {
+            self.push_node(parent, sol.head)
+        };
+        self.queue.push_back(sol);
+    }
+
+    /// Computes the part of the state that matters for its future.
+    fn transposition_key(&self, sol: &PartialSolution) -> TranspositionKey {
+        let puzzle = &self.puzzle;
+        let mut visited = VertexSet::new(puzzle);
+        let mut edges = EdgeSet::new(puzzle);
+
+        let cell_symbols = !(puzzle.squares.is_empty()
+            && puzzle.stars.is_empty()
+            && puzzle.triangles.is_empty()
+            && puzzle.polys.is_empty()
+            && puzzle.ylops.is_empty()
+            && puzzle.cancels.is_empty());
+
+        if !cell_symbols {
+            // Without symbols in the cells, all areas are valid,
+            // only the stones that the path went through matter
+            for pos in puzzle.vertex_stones.iter() {
+                if sol.visited.contains(pos) {
+                    visited.insert(pos);
                 }
-                if self.area_invalid(&new_sol.partial_area_right) {
-                    continue;
+            }
+            for edge in puzzle.edge_stones.iter() {
+                if sol.edges.contains(edge) {
+                    edges.insert(edge);
                 }
             }
 
-            if self.puzzle.ends.contains(&next) {
-                // With enough work on early pruning, this
-                // call to is_solution could be replaced with much less
-                // expensive functions for final checks only
-                if self.puzzle.is_solution(&new_sol.path) {
-                    self.solutions.push(new_sol.path.clone());
+            return TranspositionKey {
+                head: sol.head,
+                reachable: self.reachable_vertices(sol),
+                visited,
+                edges,
+                sealed: CellSet::new(puzzle),
+            };
+        }
+
+        // Sealed areas are final and were checked already, so the way the
+        // path went around inside them doesn't matter anymore. An element is
+        // relevant if it touches a cell of the puzzle that isn't sealed, or
+        // if it doesn't touch the puzzle's cells at all.
+        let relevant = |cells: &[Pos]| {
+            let mut inside = cells.iter().filter(|c| puzzle.contains_cell(c)).peekable();
+            inside.peek().is_none() || inside.any(|c| !sol.sealed.contains(c))
+        };
+
+        for pos in sol.visited.iter() {
+            let (x, y) = (pos.x, pos.y);
+            let cells = [
+                Pos::new(x - 1, y - 1),
+                Pos::new(x - 1, y),
+                Pos::new(x, y - 1),
+                Pos::new(x, y),
+            ];
+            if relevant(&cells) {
+                visited.insert(&pos);
+            }
+        }
+
+        for edge in sol.edges.iter() {
+            let (left, right) = edge.get_neighbouring_cells();
+            if relevant(&[left, right]) {
+                edges.insert(&edge);
+            }
+        }
+
+        TranspositionKey {
+            head: sol.head,
+            reachable: self.reachable_vertices(sol),
+            visited,
+            edges,
+            sealed: sol.sealed.clone(),
+        }
+    }
+
+    /// Returns the vertices that the path could still go through
+    fn reachable_vertices(&self, sol: &PartialSolution) -> VertexSet {
+        let mut reachable = VertexSet::new(&self.puzzle);
+        let mut to_visit = vec![sol.head];
+
+        while let Some(pos) = to_visit.pop() {
+            for dir in Direction::VARIANTS {
+                let next = pos.move_direction(dir);
+                if self.puzzle.contains_vertex(&next)
+                    && !self.puzzle.is_blocked(&EdgePos { pos, dir })
+                    && !sol.visited.contains(&next)
+                    && reachable.insert(&next)
+                {
+                    to_visit.push(next);
                 }
+            }
+        }
 
-                new_sol.reachable_ends -= 1;
-                if self.config.simple_end_reachability_check && new_sol.reachable_ends == 0 {
+        reachable
+    }
+
+    /// Checks the areas that the path can't reach anymore, and marks them as sealed.
+    /// Returns false if one of them is invalid.
+    fn seal_closed_regions(&mut self, sol: &mut PartialSolution) -> bool {
+        let reachable = self.reachable_vertices(sol);
+
+        // An area is still open if the path can reach one of its corners
+        let mut closed: HashMap<u32, HashSet<Pos>> = HashMap::new();
+        let mut open = HashSet::new();
+        for x in 0..self.puzzle.width {
+            for y in 0..self.puzzle.height {
+                let cell = Pos::new(x, y);
+                if !self.puzzle.contains_cell(&cell) || sol.sealed.contains(&cell) {
                     continue;
                 }
+
+                let label = sol.regions.label(&cell).unwrap();
+                let corners = [(x, y), (x + 1, y), (x, y + 1), (x + 1, y + 1)];
+                if corners
+                    .iter()
+                    .any(|&(x, y)| reachable.contains(&Pos::new(x, y)))
+                {
+                    open.insert(label);
+                }
+                closed.entry(label).or_default().insert(cell);
             }
+        }
 
-            self.queue.push_back(new_sol);
+        for (label, cells) in closed {
+            if open.contains(&label) {
+                continue;
+            }
+
+            let area = self.puzzle.area_from_cells(cells, &sol.visited, &sol.edges);
+            if !self.area_valid(&sol.edges, &area) {
+                return false;
+            }
+            for cell in area.cells.iter() {
+                sol.sealed.insert(cell);
+            }
         }
+
+        true
+    }
+
+    /// Returns true if all the areas that are not sealed yet are valid.
+    /// This is the final check for a path that reached an end.
+    fn regions_valid(&mut self, sol: &PartialSolution) -> bool {
+        if !self
+            .puzzle
+            .stones_outside_areas(&sol.visited, &sol.edges)
+            .is_empty()
+        {
+            return false;
+        }
+
+        let mut areas: HashMap<u32, HashSet<Pos>> = HashMap::new();
+        for x in 0..self.puzzle.width {
+            for y in 0..self.puzzle.height {
+                let cell = Pos::new(x, y);
+                if self.puzzle.contains_cell(&cell) && !sol.sealed.contains(&cell) {
+                    let label = sol.regions.label(&cell).unwrap();
+                    areas.entry(label).or_default().insert(cell);
+                }
+            }
+        }
+
+        areas.into_values().all(|cells| {
+            let area = self.puzzle.area_from_cells(cells, &sol.visited, &sol.edges);
+            self.area_valid(&sol.edges, &area)
+        })
+    }
+
+    /// Runs the complete validation of an area, and keeps track of its cost
+    fn area_valid(&mut self, path_edges: &EdgeSet, area: &Area) -> bool {
+        let before = Instant::now();
+        let valid = self.puzzle.is_area_valid(path_edges, area);
+        self.stats.full_validations += 1;
+        self.stats.validation_time += before.elapsed();
+        valid
     }
 
     /// Returns true if a superset of the given area
     /// would for sure be invalid
-    fn area_invalid(&self, area: &HashSet<Pos>) -> bool {
+    fn area_invalid(&self, area: &CellSet) -> bool {
         // Check squares
         let mut color: Option<Color> = None;
         for cell in area.iter() {
-            match self.puzzle.squares.get(cell) {
+            match self.puzzle.squares.get(&cell) {
                 Some(col) if color.get_or_insert(*col) != col => return true,
                 _ => {}
             }
@@ -209,18 +1124,17 @@ impl BFSSolver {
 
     /// Return true if it is impossible for the partial solution
     /// to result in a correct solution with regards to the stones
-    fn stones_invalid(&self, sol: &PartialSolution) -> bool {
+    /// when it goes from `prev` to `pos`, then to `next`
+    fn stones_invalid(&self, prev: Option<Pos>, pos: Pos, next: Pos) -> bool {
         // There is a stone on an edge perpandicular to the current path
         // We only need to check the last one, since previous ones were checked in other iterations
         for dir in Direction::VARIANTS {
-            // SAFETY: this function is never called with a path of one element, since that is just the start node
-            let pos = sol.path[sol.path.len() - 2];
             if self
                 .puzzle
                 .edge_stones
                 .contains(&EdgePos::new(pos.x, pos.y, dir))
-                && pos.move_direction(dir) != sol.path[sol.path.len() - 1]
-                && pos.move_direction(dir) != *sol.path.get(sol.path.len() - 3).unwrap_or(&pos)
+                && pos.move_direction(dir) != next
+                && Some(pos.move_direction(dir)) != prev
             {
                 return true;
             };
@@ -229,14 +1143,6 @@ impl BFSSolver {
     }
 }
 
-impl Iterator for BFSSolver {
-    type Item = Vec<Pos>;
-
-    fn next(&mut self) -> Option<Self::Item> {
-        todo!()
-    }
-}
-
 #[cfg(test)]
 mod bfs_tests {
     use super::*;
@@ -244,8 +1150,8 @@ mod bfs_tests {
     use std::collections::{HashMap, HashSet};
 
     fn test_solutions(puzzle: &Puzzle, expected_solutions: Vec<SolutionPath>) {
-        let mut solver = BFSSolver::new(puzzle);
-        let solutions = solver.solve();
+        let mut solver = BFSSolver::new(puzzle).unwrap();
+        let solutions = solver.solve().solutions;
 
         assert_eq!(
             solutions, expected_solutions,
@@ -254,16 +1160,14 @@ mod bfs_tests {
         )
     }
 
-    fn test_solution_count(puzzle: &Puzzle, expected_count: usize) {
-        let mut solver = BFSSolver::new(puzzle);
-        let solutions = solver.solve();
+    fn test_solution_count(puzzle: &Puzzle, expected_count: u128) {
+        let mut solver = BFSSolver::new(puzzle).unwrap();
+        let count = solver.count().count;
 
         assert_eq!(
-            solutions.len(),
-            expected_count,
+            count, expected_count,
             "Expected: {:?}\nGot: {:?}",
-            expected_count,
-            solutions.len()
+            expected_count, count
         )
     }
 
@@ -280,10 +1184,13 @@ mod bfs_tests {
 
     #[test]
     fn test_1x1_broken() {
-        let puzzle = Puzzle {
-            blocked_edges: [EdgePos::new(0, 0, Direction::Up)].into(),
-            ..Default::default()
-        };
+        let puzzle: Puzzle = r"
++---E
+    |
+S---+
+"
+        .parse()
+        .unwrap();
         let solutions = vec![SolutionPath::new(Pos::new(0, 0), "RU".into()).unwrap()];
 
         test_solutions(&puzzle, solutions);
@@ -291,10 +1198,13 @@ mod bfs_tests {
 
     #[test]
     fn test_1x1_multiple_start() {
-        let puzzle = Puzzle {
-            starts: vec![Pos::new(0, 0), Pos::new(0, 1)],
-            ..Default::default()
-        };
+        let puzzle: Puzzle = r"
+S---E
+|   |
+S---+
+"
+        .parse()
+        .unwrap();
         let solutions = vec![
             SolutionPath::new(Pos::new(0, 1), "R".into()).unwrap(),
             SolutionPath::new(Pos::new(0, 0), "UR".into()).unwrap(),
@@ -307,10 +1217,13 @@ mod bfs_tests {
 
     #[test]
     fn test_1x1_multiple_end() {
-        let puzzle = Puzzle {
-            ends: vec![Pos::new(0, 1), Pos::new(1, 1)],
-            ..Default::default()
-        };
+        let puzzle: Puzzle = r"
+E---E
+|   |
+S---+
+"
+        .parse()
+        .unwrap();
         let solutions = vec![
             SolutionPath::new(Pos::new(0, 0), "U".into()).unwrap(),
             SolutionPath::new(Pos::new(0, 0), "UR".into()).unwrap(),
@@ -323,11 +1236,13 @@ mod bfs_tests {
 
     #[test]
     fn test_1x1_stones() {
-        let puzzle = Puzzle {
-            vertex_stones: [Pos::new(1, 0)].into(),
-            edge_stones: [EdgePos::new(0, 0, Direction::Right)].into(),
-            ..Default::default()
-        };
+        let puzzle: Puzzle = r"
++---E
+|   |
+S-o-o
+"
+        .parse()
+        .unwrap();
         let solutions = vec![SolutionPath::new(Pos::new(0, 0), "RU".into()).unwrap()];
 
         test_solutions(&puzzle, solutions);
@@ -335,17 +1250,13 @@ mod bfs_tests {
 
     #[test]
     fn test_2x1_squares() {
-        // This puzzle is a 2x1 puzzle with the start in the middle
-        // at the bottom and the exit above it. The left cell contains
-        // a black square, the right one contains a white square
-        let puzzle = Puzzle {
-            width: 2,
-            height: 1,
-            starts: vec![Pos::new(1, 0)],
-            ends: vec![Pos::new(1, 1)],
-            squares: [(Pos::new(0, 0), 0), (Pos::new(1, 0), 1)].into(),
-            ..Default::default()
-        };
+        let puzzle: Puzzle = r"
++---E---+
+|[a]|[b]|
++---S---+
+"
+        .parse()
+        .unwrap();
         let solutions = vec![SolutionPath::new(Pos::new(1, 0), "U".into()).unwrap()];
 
         test_solutions(&puzzle, solutions);
@@ -353,16 +1264,13 @@ mod bfs_tests {
 
     #[test]
     fn test_2x1_stars() {
-        // This puzzle is a 2x1 puzzle with the start in the middle
-        // at the bottom and the exit above it. Both cells contain a star
-        let puzzle = Puzzle {
-            width: 2,
-            height: 1,
-            starts: vec![Pos::new(1, 0)],
-            ends: vec![Pos::new(1, 1)],
-            stars: [(Pos::new(0, 0), 0), (Pos::new(1, 0), 0)].into(),
-            ..Default::default()
-        };
+        let puzzle: Puzzle = r"
++---E---+
+|*a*|*a*|
++---S---+
+"
+        .parse()
+        .unwrap();
         let solutions = vec![
             SolutionPath::new(Pos::new(1, 0), "RUL".into()).unwrap(),
             SolutionPath::new(Pos::new(1, 0), "LUR".into()).unwrap(),
@@ -375,26 +1283,19 @@ mod bfs_tests {
     fn test_broken_star_square() {
         // This puzzle is an actual puzzle example from the game that
         // contains broken paths, stars, and squares
-        let puzzle = Puzzle {
-            blocked_edges: [
-                EdgePos::new(0, 4, Direction::Right),
-                EdgePos::new(3, 3, Direction::Right),
-                EdgePos::new(3, 1, Direction::Up),
-                EdgePos::new(2, 0, Direction::Up),
-            ]
-            .into(),
-            squares: [
-                (Pos::new(0, 0), 0),
-                (Pos::new(1, 0), 0),
-                (Pos::new(2, 2), 0),
-                (Pos::new(1, 1), 1),
-                (Pos::new(2, 3), 1),
-                (Pos::new(3, 3), 1),
-            ]
-            .into(),
-            stars: [(Pos::new(3, 0), 5), (Pos::new(0, 3), 5)].into(),
-            ..Puzzle::default_with_size(4, 4)
-        };
+        let puzzle: Puzzle = r"
++   +---+---+---E
+|*f*|   |[b]|[b]|
++---+---+---+   +
+|   |   |[a]|   |
++---+---+---+---+
+|   |[b]|       |
++---+---+---+---+
+|[a]|[a]    |*f*|
+S---+---+---+---+
+"
+        .parse()
+        .unwrap();
         let solutions = vec![
             SolutionPath::new(Pos::new(0, 0), "RRRRULLLURRULURR".into()).unwrap(),
             SolutionPath::new(Pos::new(0, 0), "UUURRRDLLDRRDRUUUU".into()).unwrap(),
@@ -407,18 +1308,19 @@ mod bfs_tests {
 
     #[test]
     fn test_triangles() {
-        let puzzle = Puzzle {
-            triangles: [
-                (Pos::new(0, 3), 3),
-                (Pos::new(1, 3), 1),
-                (Pos::new(2, 2), 2),
-                (Pos::new(3, 2), 2),
-                (Pos::new(3, 1), 3),
-                (Pos::new(2, 0), 1),
-            ]
-            .into(),
-            ..Puzzle::default_with_size(4, 4)
-        };
+        let puzzle: Puzzle = r"
++---+---+---+---E
+|^^^| ^ |   |   |
++---+---+---+---+
+|   |   |^^ |^^ |
++---+---+---+---+
+|   |   |   |^^^|
++---+---+---+---+
+|   |   | ^ |   |
+S---+---+---+---+
+"
+        .parse()
+        .unwrap();
         let solution =
             SolutionPath::new(Pos::new(0, 0), "UURULURRRDLDDLDRRRULURUU".into()).unwrap();
         test_solutions(&puzzle, vec![solution]);
@@ -426,17 +1328,16 @@ mod bfs_tests {
 
     #[test]
     fn test_poly() {
-        let puzzle = Puzzle {
-            polys: [(
-                Pos::new(1, 0),
-                Poly {
-                    rotatable: false,
-                    minos: [Pos::new(0, 0), Pos::new(1, 0)].into(),
-                },
-            )]
-            .into(),
-            ..Puzzle::default_with_size(3, 1)
-        };
+        let puzzle: Puzzle = r"
++---+---+---E
+|   |P1 |   |
+S---+---+---+
+
+P1
+##
+"
+        .parse()
+        .unwrap();
         let solutions = vec![
             SolutionPath::new(Pos::new(0, 0), "RURR".into()).unwrap(),
             SolutionPath::new(Pos::new(0, 0), "RRUR".into()).unwrap(),
@@ -449,79 +1350,391 @@ mod bfs_tests {
 
     #[test]
     fn test_polys() {
-        let puzzle = Puzzle {
-            polys: [
-                (
-                    Pos::new(1, 1),
-                    Poly {
-                        rotatable: false,
-                        minos: [
-                            Pos::new(0, 0),
-                            Pos::new(1, 0),
-                            Pos::new(0, 1),
-                            Pos::new(1, 1),
-                        ]
-                        .into(),
-                    },
-                ),
-                (
-                    Pos::new(2, 1),
-                    Poly {
-                        rotatable: false,
-                        minos: [Pos::new(0, 0)].into(),
-                    },
-                ),
-            ]
-            .into(),
-            ..Puzzle::default_with_size(3, 3)
-        };
+        let puzzle: Puzzle = r"
++---+---+---E
+|   |   |   |
++---+---+---+
+|   |P1 |P2 |
++---+---+---+
+|   |   |   |
+S---+---+---+
+
+P1
+##
+##
+
+P2
+#
+"
+        .parse()
+        .unwrap();
 
         test_solution_count(&puzzle, 17)
     }
 
     #[test]
     fn test_poly_rotation() {
-        let puzzle = Puzzle {
-            polys: [(
-                Pos::new(1, 1),
-                Poly {
-                    rotatable: true,
-                    minos: [
-                        Pos::new(0, 0),
-                        Pos::new(1, 0),
-                        Pos::new(0, 1),
-                        Pos::new(-1, 0),
-                    ]
-                    .into(),
-                },
-            )]
-            .into(),
-            ..Puzzle::default_with_size(3, 3)
-        };
+        let puzzle: Puzzle = r"
++---+---+---E
+|   |   |   |
++---+---+---+
+|   |P1 |   |
++---+---+---+
+|   |   |   |
+S---+---+---+
+
+P1 rotatable
+.#.
+###
+"
+        .parse()
+        .unwrap();
 
         test_solution_count(&puzzle, 12);
     }
 
     #[test]
     fn test_cancel() {
-        let puzzle = Puzzle {
-            cancels: [(Pos::new(0, 0), 0)].into(),
-            stars: [(Pos::new(1, 0), 1)].into(),
-            ..Puzzle::default_with_size(4, 1)
-        };
+        let puzzle: Puzzle = r"
++---+---+---+---E
+|\a/|*b*|   |   |
+S---+---+---+---+
+"
+        .parse()
+        .unwrap();
 
         test_solution_count(&puzzle, 8)
     }
 
     #[test]
     fn test_cancel_and_hexagons() {
-        let puzzle = Puzzle {
-            cancels: [(Pos::new(0, 1), 0)].into(),
-            edge_stones: [EdgePos::new(1, 1, Direction::Up)].into(),
-            vertex_stones: [Pos::new(1, 1)].into(),
-            ..Puzzle::default_with_size(4, 4)
-        };
+        let puzzle: Puzzle = r"
++---+---+---+---E
+|   |   |   |   |
++---+---+---+---+
+|   |   |   |   |
++---+---+---+---+
+|\a/o   |   |   |
++---o---+---+---+
+|   |   |   |   |
+S---+---+---+---+
+"
+        .parse()
+        .unwrap();
 
         test_solution_count(&puzzle, 3678)
     }
+
+    #[test]
+    fn test_count_mode() {
+        let puzzle = Puzzle::default_with_size(3, 3);
+        let mut solver = BFSSolver::new(&puzzle).unwrap();
+        let solutions = solver.solve().solutions;
+        let solve_memory = solver.stats.peak_memory;
+
+        // Without merging, count mode visits the same states but keeps no path
+        let mut solver = BFSSolver::new(&puzzle).unwrap();
+        solver.config.merge_transpositions = false;
+        let result = solver.count();
+        assert_eq!(result.count, solutions.len() as u128);
+        assert!(result.solutions.is_empty());
+        assert!(solver.arena.is_empty());
+        assert!(solver.solutions.is_empty());
+        assert!(solver.stats.peak_memory < solve_memory);
+    }
+
+    #[test]
+    fn test_count_transpositions() {
+        // Squares and stones make sure that merged states keep what their checks need
+        let puzzle: Puzzle = r"
++---+---+---+---E
+|   |   |   |[b]|
++---+-o-+---+---+
+|   |   |   |   |
++---+---o---+---+
+|   |   |[b]|   |
++---+---+---+---+
+|[a]|   |   |   |
+S---+---+---+---+
+"
+        .parse()
+        .unwrap();
+        let expected = BFSSolver::new(&puzzle).unwrap().solve().count;
+
+        let mut solver = BFSSolver::new(&puzzle).unwrap();
+        assert_eq!(solver.count().count, expected);
+        assert!(solver.stats.states_merged > 0);
+
+        // On an empty grid, only the head and the reachable vertices matter
+        let puzzle = Puzzle::default_with_size(4, 4);
+        let mut solver = BFSSolver::new(&puzzle).unwrap();
+        solver.config.merge_transpositions = false;
+        assert_eq!(solver.count().count, 8512);
+        let unmerged_states = solver.stats.states_visited;
+
+        let mut solver = BFSSolver::new(&puzzle).unwrap();
+        assert_eq!(solver.count().count, 8512);
+        assert!(solver.stats.states_visited * 5 < unmerged_states);
+    }
+
+    #[test]
+    fn test_limits() {
+        let puzzle = Puzzle::default_with_size(4, 4);
+
+        let mut solver = BFSSolver::new(&puzzle).unwrap();
+        let result = solver.count();
+        assert_eq!(result.outcome, SearchOutcome::Finished);
+        assert!(result.is_complete());
+
+        solver.config.max_states = 100;
+        let result = solver.count();
+        assert_eq!(result.outcome, SearchOutcome::LimitReached(Limit::States));
+        assert!(!result.is_complete());
+
+        let mut solver = BFSSolver::new(&puzzle).unwrap();
+        solver.config.max_solutions = 3;
+        let result = solver.solve();
+        assert_eq!(result.solutions.len(), 3);
+        assert_eq!(result.count, 3);
+        assert_eq!(
+            result.outcome,
+            SearchOutcome::LimitReached(Limit::Solutions)
+        );
+
+        // Solving again searches with the new limits
+        solver.config.max_solutions = 0;
+        let result = solver.solve();
+        assert_eq!(result.outcome, SearchOutcome::Finished);
+        assert!(result.solutions.len() > 3);
+
+        let mut solver = BFSSolver::new(&puzzle).unwrap();
+        solver.config.max_queue_size = 10;
+        assert_eq!(
+            solver.count().outcome,
+            SearchOutcome::LimitReached(Limit::QueueSize)
+        );
+
+        let mut solver = BFSSolver::new(&puzzle).unwrap();
+        solver.config.time_limit = Some(Duration::ZERO);
+        assert_eq!(
+            solver.count().outcome,
+            SearchOutcome::LimitReached(Limit::Time)
+        );
+
+        let cancel = Arc::new(AtomicBool::new(true));
+        let mut solver = BFSSolver::new(&puzzle).unwrap();
+        solver.config.cancel = Some(cancel.clone());
+        let result = solver.count();
+        assert_eq!(result.count, 0);
+        assert_eq!(result.outcome, SearchOutcome::Cancelled);
+    }
+
+    #[test]
+    fn test_progress() {
+        let reports = std::rc::Rc::new(std::cell::RefCell::new(vec![]));
+        let mut solver = BFSSolver::new(&Puzzle::default_with_size(4, 4)).unwrap();
+        solver.config.progress_interval = Duration::ZERO;
+        solver.config.progress = Some(Box::new({
+            let reports = reports.clone();
+            move |progress: &Progress| reports.borrow_mut().push(progress.clone())
+        }));
+        solver.count();
+
+        // Every 1024 states, then once at the end
+        let reports = reports.borrow();
+        assert_eq!(
+            reports.len() as u64,
+            solver.stats.states_visited.div_ceil(1024) + 1
+        );
+        let last = reports.last().unwrap();
+        assert_eq!(last.solutions, 8512);
+        assert_eq!(last.queue_size, 0);
+        assert_eq!(last.stats.states_visited, solver.stats.states_visited);
+        assert!(last.stats.pruned() > 0);
+    }
+
+    #[test]
+    fn test_arena_compaction() {
+        let puzzle = Puzzle::default_with_size(3, 3);
+        let mut solver = BFSSolver::new(&puzzle).unwrap();
+        let solutions = solver.solve().solutions;
+
+        // Most candidates were discarded, only the solutions' vertices are left
+        assert!(solver.arena.len() < solver.stats.states_visited as usize);
+        let distinct: HashSet<_> = solutions.iter().map(ToString::to_string).collect();
+        assert_eq!(distinct.len(), solutions.len());
+        assert!(solutions.iter().all(|path| puzzle.is_solution(path)));
+        assert_eq!(solutions.len() as u128, solver.count().count);
+    }
+
+    #[test]
+    fn test_solve_from() {
+        let puzzle: Puzzle = r"
++---+---+---E
+|   |   |   |
++---+---+---+
+|   |[b]|   |
++---+---+---+
+|[a]|   |   |
+S---+---+---+
+"
+        .parse()
+        .unwrap();
+        let mut solver = BFSSolver::new(&puzzle).unwrap();
+        let solutions = solver.solve().solutions;
+        let path = |dirs: &str| SolutionPath::new(Pos::new(0, 0), dirs.into()).unwrap();
+
+        for dirs in ["", "U", "R", "UUR", "RRRUUU"] {
+            let prefix = path(dirs);
+            let expected: Vec<_> = solutions
+                .iter()
+                .filter(|solution| solution.starts_with(&prefix))
+                .cloned()
+                .collect();
+            let result = solver.solve_from(&prefix).unwrap();
+            assert_eq!(result.solutions, expected, "{prefix}");
+            assert!(result.is_complete());
+            let result = solver.count_from(&prefix).unwrap();
+            assert_eq!(result.count, expected.len() as u128, "{prefix}");
+        }
+
+        assert_eq!(solver.count_from(&path("RRRUUU")).unwrap().count, 0);
+        assert_eq!(
+            solver.solve_from(&solutions[0]).unwrap().solutions,
+            vec![solutions[0].clone()]
+        );
+        assert_eq!(
+            solver.solve_from(&path("UD")),
+            Err(PathError::SelfIntersection(Pos::new(0, 0)))
+        );
+        assert_eq!(solver.solve().solutions, solutions);
+    }
+
+    #[test]
+    fn test_check_uniqueness() {
+        let puzzle: Puzzle = r"
+S---+
+|^^^|
++---E
+"
+        .parse()
+        .unwrap();
+        let mut solver = BFSSolver::new(&puzzle).unwrap();
+        assert_eq!(solver.check_uniqueness(), Uniqueness::Unsolvable);
+
+        let puzzle: Puzzle = r"
++---E---+
+|[a]|[b]|
+S---X---+
+"
+        .parse()
+        .unwrap();
+        let path = |x, dirs: &str| SolutionPath::new(Pos::new(x, 0), dirs.into()).unwrap();
+        let mut solver = BFSSolver::new(&puzzle).unwrap();
+        assert_eq!(
+            solver.check_uniqueness(),
+            Uniqueness::Multiple(path(1, "U"), path(0, "RU"))
+        );
+        assert_eq!(solver.solve().count, 3);
+
+        // All three solutions put the squares in two areas of one cell
+        assert_eq!(
+            puzzle.partition(&path(0, "URD")),
+            Ok(vec![Some(0), Some(1)])
+        );
+        solver.config.uniqueness_by_regions = true;
+        assert_eq!(solver.check_uniqueness(), Uniqueness::Unique(path(1, "U")));
+
+        // A limit can stop the search before the second solution
+        solver.config.uniqueness_by_regions = false;
+        solver.config.max_states = 2;
+        assert_eq!(
+            solver.check_uniqueness(),
+            Uniqueness::Incomplete(
+                Some(path(1, "U")),
+                SearchOutcome::LimitReached(Limit::States)
+            )
+        );
+        solver.config.time_limit = Some(Duration::ZERO);
+        assert_eq!(
+            solver.check_uniqueness(),
+            Uniqueness::Incomplete(None, SearchOutcome::LimitReached(Limit::Time))
+        );
+    }
+
+    #[test]
+    fn test_hint() {
+        let puzzle: Puzzle = r"
++---E---+
+|[a]|[b]|
+S---X---+
+"
+        .parse()
+        .unwrap();
+        let mut solver = BFSSolver::new(&puzzle).unwrap();
+        let mut hint =
+            |dirs: &str| solver.hint(&SolutionPath::new(Pos::new(0, 0), dirs.into()).unwrap());
+
+        assert_eq!(
+            hint(""),
+            Ok(Hint {
+                moves: vec![Direction::Up, Direction::Right],
+                unknown: vec![],
+                complete: false,
+            })
+        );
+        assert_eq!(hint("UR").unwrap().moves, vec![Direction::Down]);
+        assert!(hint("URR").unwrap().is_dead_end());
+
+        // A solution that can't be extended is not a dead end
+        let solved = hint("RU").unwrap();
+        assert!(solved.complete && solved.moves.is_empty());
+        assert!(!solved.is_dead_end());
+        assert_eq!(hint("UU"), Err(PathError::OutOfBounds(Pos::new(0, 2))));
+
+        let prefix = SolutionPath::new(Pos::new(1, 1), "L".into()).unwrap();
+        assert_eq!(
+            solver.hint(&prefix),
+            Err(PathError::NotAStart(Pos::new(1, 1)))
+        );
+
+        // Without any time left, no move can be searched
+        solver.config.time_limit = Some(Duration::ZERO);
+        let start = SolutionPath::new(Pos::new(0, 0), "".into()).unwrap();
+        assert_eq!(
+            solver.hint(&start).unwrap().unknown,
+            vec![Direction::Up, Direction::Right]
+        );
+    }
+
+    #[test]
+    fn test_stats() {
+        let puzzle: Puzzle = r"
++---+---+---E
+|   |   |   |
++---+-o-+---+
+|   |[b]|   |
++---+---+---+
+|[a]|   |   |
+S---+---+---+
+"
+        .parse()
+        .unwrap();
+        let mut solver = BFSSolver::new(&puzzle).unwrap();
+        solver.solve();
+
+        let stats = &solver.stats;
+        assert!(stats.pruned_edge_stones > 0);
+        assert!(stats.pruned_partial_areas > 0);
+        assert!(stats.pruned_end_reachability > 0);
+        assert!(stats.pruned_closed_areas > 0);
+        assert!(stats.full_validations > 0);
+        assert!(stats.peak_queue_size > 0);
+
+        // Every search starts counting from zero
+        solver.count();
+        let counted = solver.stats.clone();
+        solver.count();
+        assert_eq!(solver.stats.states_visited, counted.states_visited);
+    }
 }
@@ -0,0 +1,497 @@
+use std::fmt::{self, Write};
+
+use crate::puzzle::*;
+
+/// Size of a cell, in pixels
+const CELL: f32 = 80.0;
+/// Space around a panel, enough for the ends to stick out
+const MARGIN: f32 = 40.0;
+const LINE_WIDTH: f32 = 16.0;
+
+const BACKGROUND: &str = "#2a3140";
+const LINE_COLOR: &str = "#8a94a6";
+const PATH_COLOR: &str = "#f5e050";
+const STONE_COLOR: &str = "#15181e";
+const TRIANGLE_COLOR: &str = "#f0a030";
+const POLY_COLOR: &str = "#f0d040";
+const YLOP_COLOR: &str = "#4080ff";
+const VIOLATION_COLOR: &str = "#ff2020";
+
+/// Colours of the squares, stars and cancellers, by their index
+const PALETTE: [&str; 8] = [
+    "#111111", "#ffffff", "#e04040", "#4070e0", "#40b040", "#f09020", "#b050d0", "#30c0c0",
+];
+
+/// Fill colours of the areas, drawn with some transparency
+const AREA_PALETTE: [&str; 6] = [
+    "#e06666", "#6fa8dc", "#93c47d", "#ffd966", "#c27ba0", "#76a5af",
+];
+
+#[derive(Clone, Debug)]
+pub struct SvgConfig {
+    /// Fill the areas that the path splits the puzzle into
+    pub areas: bool,
+    /// Circle the symbols and stones that the path doesn't satisfy
+    pub violations: bool,
+    /// Number of panels per row, when drawing several paths
+    pub columns: usize,
+}
+
+impl Default for SvgConfig {
+    fn default() -> Self {
+        Self {
+            areas: true,
+            violations: true,
+            columns: 4,
+        }
+    }
+}
+
+/// Draws the puzzle, with the path over it if there is one
+pub fn render(puzzle: &Puzzle, path: Option<&SolutionPath>, config: &SvgConfig) -> String {
+    let (width, height) = panel_size(puzzle);
+    let mut res = header(width, height);
+    draw_panel(&mut res, puzzle, path, config).expect("writing to a String can't fail");
+    res.push_str("</svg>\n");
+    res
+}
+
+/// Draws one panel per path, laid out in rows of `config.columns` panels.
+/// Without any path, this draws the puzzle alone.
+pub fn render_grid(puzzle: &Puzzle, paths: &[SolutionPath], config: &SvgConfig) -> String {
+    if paths.is_empty() {
+        return render(puzzle, None, config);
+    }
+
+    let columns = config.columns.clamp(1, paths.len());
+    let rows = paths.len().div_ceil(columns);
+    let (width, height) = panel_size(puzzle);
+    let mut res = header(width * columns as f32, height * rows as f32);
+
+    for (idx, path) in paths.iter().enumerate() {
+        let x = (idx % columns) as f32 * width;
+        let y = (idx / columns) as f32 * height;
+        res.push_str(&format!("<g transform=\"translate({x} {y})\">\n"));
+        draw_panel(&mut res, puzzle, Some(path), config).expect("writing to a String can't fail");
+        res.push_str("</g>\n");
+    }
+
+    res.push_str("</svg>\n");
+    res
+}
+
+fn panel_size(puzzle: &Puzzle) -> (f32, f32) {
+    (
+        puzzle.width as f32 * CELL + 2.0 * MARGIN,
+        puzzle.height as f32 * CELL + 2.0 * MARGIN,
+    )
+}
+
+fn header(width: f32, height: f32) -> String {
+    format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{height}\" \
+         viewBox=\"0 0 {width} {height}\">\n"
+    )
+}
+
+/// Colours past the palette get hues a golden angle apart, so that no two
+/// colours are drawn the same
+fn color(color: Color) -> String {
+    match PALETTE.get(color as usize) {
+        Some(fill) => fill.to_string(),
+        None => {
+            let hue = (color as usize - PALETTE.len()) as f32 * 137.508 % 360.0;
+            format!("hsl({hue:.2}, 70%, 55%)")
+        }
+    }
+}
+
+/// Maps puzzle coordinates to the drawing, where y goes down
+struct Frame {
+    height: Coord,
+}
+
+impl Frame {
+    fn vertex(&self, pos: &Pos) -> (f32, f32) {
+        (
+            MARGIN + pos.x as f32 * CELL,
+            MARGIN + (self.height - pos.y) as f32 * CELL,
+        )
+    }
+
+    fn cell_center(&self, pos: &Pos) -> (f32, f32) {
+        let (x, y) = self.vertex(pos);
+        (x + CELL / 2.0, y - CELL / 2.0)
+    }
+
+    fn edge_center(&self, edge: &EdgePos) -> (f32, f32) {
+        let [from, to] = edge.get_neighbouring_corners().map(|pos| self.vertex(&pos));
+        ((from.0 + to.0) / 2.0, (from.1 + to.1) / 2.0)
+    }
+}
+
+fn draw_panel(
+    out: &mut String,
+    puzzle: &Puzzle,
+    path: Option<&SolutionPath>,
+    config: &SvgConfig,
+) -> fmt::Result {
+    let frame = Frame {
+        height: puzzle.height,
+    };
+    let (width, height) = panel_size(puzzle);
+    writeln!(
+        out,
+        r#"<rect width="{width}" height="{height}" rx="12" fill="{BACKGROUND}"/>"#
+    )?;
+
+    // The areas can only be computed for a path that can be drawn on the puzzle
+    let areas = path.and_then(|path| puzzle.areas(path).ok());
+    if let (Some(areas), true) = (areas, config.areas) {
+        for (idx, area) in areas.iter().enumerate() {
+            let fill = AREA_PALETTE[idx % AREA_PALETTE.len()];
+            for cell in &area.cells {
+                let (x, y) = frame.vertex(cell);
+                writeln!(
+                    out,
+                    r#"<rect class="area" x="{x}" y="{}" width="{CELL}" height="{CELL}" fill="{fill}" fill-opacity="0.3"/>"#,
+                    y - CELL
+                )?;
+            }
+        }
+    }
+
+    draw_grid(out, puzzle, &frame)?;
+    for x in 0..puzzle.width {
+        for y in 0..puzzle.height {
+            draw_symbol(out, puzzle, &frame, &Pos::new(x, y))?;
+        }
+    }
+
+    if let Some(path) = path {
+        let points: Vec<String> = path
+            .iter()
+            .map(|pos| {
+                let (x, y) = frame.vertex(pos);
+                format!("{x},{y}")
+            })
+            .collect();
+        if let Some(start) = path.first() {
+            let (x, y) = frame.vertex(start);
+            writeln!(
+                out,
+                r#"<circle cx="{x}" cy="{y}" r="{}" fill="{PATH_COLOR}"/>"#,
+                LINE_WIDTH * 1.4
+            )?;
+        }
+        writeln!(
+            out,
+            r#"<polyline class="path" points="{}" fill="none" stroke="{PATH_COLOR}" stroke-width="{LINE_WIDTH}" stroke-linecap="round" stroke-linejoin="round"/>"#,
+            points.join(" ")
+        )?;
+    }
+
+    // Stones go over the path, so that crossed ones stay visible
+    for stone in &puzzle.vertex_stones {
+        let (x, y) = frame.vertex(stone);
+        draw_hexagon(out, x, y)?;
+    }
+    for stone in &puzzle.edge_stones {
+        let (x, y) = frame.edge_center(stone);
+        draw_hexagon(out, x, y)?;
+    }
+
+    if let (Some(path), true) = (path, config.violations) {
+        draw_violations(out, puzzle, &frame, path)?;
+    }
+    Ok(())
+}
+
+fn draw_grid(out: &mut String, puzzle: &Puzzle, frame: &Frame) -> fmt::Result {
+    let line = |out: &mut String, (x1, y1): (f32, f32), (x2, y2): (f32, f32)| {
+        writeln!(
+            out,
+            r#"<line x1="{x1}" y1="{y1}" x2="{x2}" y2="{y2}" stroke="{LINE_COLOR}" stroke-width="{LINE_WIDTH}" stroke-linecap="round"/>"#
+        )
+    };
+
+    for x in 0..=puzzle.width {
+        for y in 0..=puzzle.height {
+            for dir in [Direction::Up, Direction::Right] {
+                let edge = EdgePos::new(x, y, dir);
+                let [from, to] = edge.get_neighbouring_corners();
+                if !puzzle.contains_vertex(&to) {
+                    continue;
+                }
+
+                let (from, to) = (frame.vertex(&from), frame.vertex(&to));
+                if !puzzle.is_blocked(&edge) {
+                    line(out, from, to)?;
+                    continue;
+                }
+
+                // Broken edges are drawn with a gap in the middle, unless they
+                // only separate cells that are outside of the puzzle
+                let (left, right) = edge.get_neighbouring_cells();
+                if puzzle.contains_cell(&left) || puzzle.contains_cell(&right) {
+                    let lerp =
+                        |t: f32| (from.0 + (to.0 - from.0) * t, from.1 + (to.1 - from.1) * t);
+                    line(out, from, lerp(0.3))?;
+                    line(out, lerp(0.7), to)?;
+                }
+            }
+        }
+    }
+
+    for start in &puzzle.starts {
+        let (x, y) = frame.vertex(start);
+        writeln!(
+            out,
+            r#"<circle cx="{x}" cy="{y}" r="{}" fill="{LINE_COLOR}"/>"#,
+            LINE_WIDTH * 1.4
+        )?;
+    }
+
+    // Ends stick out of the side of the puzzle they are on
+    for end in &puzzle.ends {
+        let (dx, dy) = if end.y == puzzle.height {
+            (0.0, -1.0)
+        } else if end.y == 0 {
+            (0.0, 1.0)
+        } else if end.x == 0 {
+            (-1.0, 0.0)
+        } else if end.x == puzzle.width {
+            (1.0, 0.0)
+        } else {
+            (0.0, -1.0)
+        };
+        let (x, y) = frame.vertex(end);
+        let length = MARGIN * 0.6;
+        line(out, (x, y), (x + dx * length, y + dy * length))?;
+    }
+    Ok(())
+}
+
+fn draw_symbol(out: &mut String, puzzle: &Puzzle, frame: &Frame, pos: &Pos) -> fmt::Result {
+    let (cx, cy) = frame.cell_center(pos);
+
+    if let Some(c) = puzzle.squares.get(pos) {
+        let size = CELL * 0.3;
+        writeln!(
+            out,
+            r#"<rect x="{}" y="{}" width="{size}" height="{size}" rx="{}" fill="{}"/>"#,
+            cx - size / 2.0,
+            cy - size / 2.0,
+            size / 4.0,
+            color(*c)
+        )?;
+    } else if let Some(c) = puzzle.stars.get(pos) {
+        // Two squares, one of them turned, make an eight pointed star
+        let size = CELL * 0.26;
+        for angle in [0, 45] {
+            writeln!(
+                out,
+                r#"<rect x="{}" y="{}" width="{size}" height="{size}" fill="{}" transform="rotate({angle} {cx} {cy})"/>"#,
+                cx - size / 2.0,
+                cy - size / 2.0,
+                color(*c)
+            )?;
+        }
+    } else if let Some(count) = puzzle.triangles.get(pos) {
+        let size = CELL * 0.16;
+        let spacing = size * 1.3;
+        let first = cx - spacing * (*count as f32 - 1.0) / 2.0;
+        for idx in 0..*count {
+            let x = first + spacing * idx as f32;
+            writeln!(
+                out,
+                r#"<polygon points="{},{} {},{} {},{}" fill="{TRIANGLE_COLOR}"/>"#,
+                x,
+                cy - size / 2.0,
+                x - size / 2.0,
+                cy + size / 2.0,
+                x + size / 2.0,
+                cy + size / 2.0
+            )?;
+        }
+    } else if let Some(poly) = puzzle.polys.get(pos) {
+        draw_poly(out, poly, cx, cy, true)?;
+    } else if let Some(poly) = puzzle.ylops.get(pos) {
+        draw_poly(out, poly, cx, cy, false)?;
+    } else if let Some(c) = puzzle.cancels.get(pos) {
+        // Y shape, with branches 120° apart
+        let length = CELL * 0.18;
+        for (dx, dy) in [(0.0, 1.0), (-0.866, -0.5), (0.866, -0.5)] {
+            writeln!(
+                out,
+                r#"<line x1="{cx}" y1="{cy}" x2="{}" y2="{}" stroke="{}" stroke-width="{}" stroke-linecap="round"/>"#,
+                cx + dx * length,
+                cy + dy * length,
+                color(*c),
+                CELL * 0.07
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Draws the blocks of a polyomino, filled for polys and hollow for ylops.
+/// Rotatable shapes are drawn tilted, like in the game.
+fn draw_poly(out: &mut String, poly: &Poly, cx: f32, cy: f32, filled: bool) -> fmt::Result {
+    let Some(min_x) = poly.minos.iter().map(|p| p.x).min() else {
+        return Ok(());
+    };
+    let max_x = poly.minos.iter().map(|p| p.x).max().unwrap();
+    let min_y = poly.minos.iter().map(|p| p.y).min().unwrap();
+    let max_y = poly.minos.iter().map(|p| p.y).max().unwrap();
+
+    let span = (max_x - min_x).max(max_y - min_y) as f32 + 1.0;
+    let block = (CELL * 0.6 / span).min(CELL * 0.15);
+    let left = cx - block * (max_x - min_x + 1) as f32 / 2.0;
+    let top = cy - block * (max_y - min_y + 1) as f32 / 2.0;
+
+    let style = if filled {
+        format!(r#"fill="{POLY_COLOR}""#)
+    } else {
+        format!(
+            r#"fill="none" stroke="{YLOP_COLOR}" stroke-width="{}""#,
+            block * 0.2
+        )
+    };
+    let rotation = if poly.rotatable { 15 } else { 0 };
+    writeln!(out, r#"<g transform="rotate({rotation} {cx} {cy})">"#)?;
+    for mino in &poly.minos {
+        let x = left + (mino.x - min_x) as f32 * block;
+        let y = top + (max_y - mino.y) as f32 * block;
+        writeln!(
+            out,
+            r#"<rect x="{}" y="{}" width="{}" height="{}" {style}/>"#,
+            x + block * 0.1,
+            y + block * 0.1,
+            block * 0.8,
+            block * 0.8
+        )?;
+    }
+    out.push_str("</g>\n");
+    Ok(())
+}
+
+fn draw_hexagon(out: &mut String, x: f32, y: f32) -> fmt::Result {
+    let radius = LINE_WIDTH * 0.55;
+    let points: Vec<String> = (0..6)
+        .map(|idx| {
+            let angle = std::f32::consts::FRAC_PI_3 * idx as f32;
+            format!("{},{}", x + radius * angle.cos(), y + radius * angle.sin())
+        })
+        .collect();
+    writeln!(
+        out,
+        r#"<polygon points="{}" fill="{STONE_COLOR}"/>"#,
+        points.join(" ")
+    )
+}
+
+/// Circles what the path gets wrong. A path that can't be drawn
+/// gets a frame around the whole panel.
+fn draw_violations(
+    out: &mut String,
+    puzzle: &Puzzle,
+    frame: &Frame,
+    path: &SolutionPath,
+) -> fmt::Result {
+    let Err(violations) = puzzle.validate(path) else {
+        return Ok(());
+    };
+
+    let circle = |out: &mut String, (x, y): (f32, f32), radius: f32| {
+        writeln!(
+            out,
+            r#"<circle class="violation" cx="{x}" cy="{y}" r="{radius}" fill="none" stroke="{VIOLATION_COLOR}" stroke-width="4"/>"#
+        )
+    };
+
+    for violation in &violations {
+        match &violation.kind {
+            ViolationKind::Cell(pos, _) => circle(out, frame.cell_center(pos), CELL * 0.35)?,
+            ViolationKind::VertexStone(pos) => circle(out, frame.vertex(pos), LINE_WIDTH)?,
+            ViolationKind::EdgeStone(edge) => circle(out, frame.edge_center(edge), LINE_WIDTH)?,
+            ViolationKind::Path(_) => {
+                let (width, height) = panel_size(puzzle);
+                writeln!(
+                    out,
+                    r#"<rect class="violation" x="2" y="2" width="{}" height="{}" rx="12" fill="none" stroke="{VIOLATION_COLOR}" stroke-width="4"/>"#,
+                    width - 4.0,
+                    height - 4.0
+                )?;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    #[test]
+    fn test_render_violations() {
+        let puzzle: Puzzle = r"
++---E---+
+|[a]|[b]|
++---S---+
+"
+        .parse()
+        .unwrap();
+        let config = SvgConfig::default();
+
+        let solution = SolutionPath::new(Pos::new(1, 0), "U".into()).unwrap();
+        let svg = render(&puzzle, Some(&solution), &config);
+        assert!(svg.starts_with("<svg"));
+        assert_eq!(svg.matches(r#"class="area""#).count(), 2);
+        assert_eq!(svg.matches(r#"class="violation""#).count(), 0);
+
+        // Both squares end up in the same area, the second one is wrong
+        let wrong = SolutionPath::new(Pos::new(1, 0), "RUL".into()).unwrap();
+        let svg = render(&puzzle, Some(&wrong), &config);
+        assert_eq!(svg.matches(r#"class="violation""#).count(), 1);
+
+        // A path that goes through the puzzle's walls
+        let invalid = SolutionPath::new(Pos::new(1, 0), "DU".into()).unwrap();
+        let svg = render(&puzzle, Some(&invalid), &config);
+        assert_eq!(svg.matches(r#"class="area""#).count(), 0);
+        assert!(svg.contains(r#"<rect class="violation""#));
+    }
+
+    #[test]
+    fn test_colors() {
+        let colors: HashSet<_> = (0..=Color::MAX).map(color).collect();
+        assert_eq!(colors.len(), Color::MAX as usize + 1);
+        assert_eq!(color(0), PALETTE[0]);
+    }
+
+    #[test]
+    fn test_render_grid() {
+        let puzzle = Puzzle::default_with_size(2, 2);
+        let paths = vec![
+            SolutionPath::new(Pos::new(0, 0), "UURR".into()).unwrap(),
+            SolutionPath::new(Pos::new(0, 0), "RRUU".into()).unwrap(),
+            SolutionPath::new(Pos::new(0, 0), "RUUR".into()).unwrap(),
+        ];
+        let config = SvgConfig {
+            columns: 2,
+            ..Default::default()
+        };
+
+        let svg = render_grid(&puzzle, &paths, &config);
+        let (width, height) = panel_size(&puzzle);
+        assert!(svg.contains(&format!(
+            r#"width="{}" height="{}""#,
+            width * 2.0,
+            height * 2.0
+        )));
+        assert_eq!(svg.matches(r#"class="path""#).count(), 3);
+        assert_eq!(svg.matches("<g transform=\"translate").count(), 3);
+    }
+}
@@ -0,0 +1,278 @@
+//! Differential tests of the pruning features of the solver.
+//!
+//! Random 4x4 and 5x5 puzzles are solved by brute force, trying every path
+//! and checking it with `Puzzle::is_solution`, and by the solver with every
+//! combination of search features. All of them must find the same solutions.
+
+use std::collections::BTreeSet;
+
+use witness_solver::{
+    puzzle::*,
+    solver::{BFSSolver, BFSSolverConfig},
+};
+
+/// Number of solvable random puzzles to check
+const PUZZLES: usize = 4;
+
+/// Number of paths above which a random puzzle is skipped
+const MAX_PATHS: usize = 500;
+
+/// Small xorshift generator, so that failures can be reproduced from the seed
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /// Random number in `0..n`
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    fn chance(&mut self, percent: usize) -> bool {
+        self.below(100) < percent
+    }
+
+    fn vertex(&mut self, puzzle: &Puzzle) -> Pos {
+        Pos::new(
+            self.below(puzzle.width as usize + 1) as Coord,
+            self.below(puzzle.height as usize + 1) as Coord,
+        )
+    }
+
+    fn cell(&mut self, puzzle: &Puzzle) -> Pos {
+        Pos::new(
+            self.below(puzzle.width as usize) as Coord,
+            self.below(puzzle.height as usize) as Coord,
+        )
+    }
+
+    fn edge(&mut self, puzzle: &Puzzle) -> EdgePos {
+        let dir = Direction::VARIANTS[self.below(4)];
+        EdgePos {
+            pos: self.vertex(puzzle),
+            dir,
+        }
+    }
+
+    /// Connected polyomino of 1 to 3 blocks
+    fn poly(&mut self) -> Poly {
+        let mut minos = vec![Pos::new(0, 0)];
+        for _ in 0..self.below(3) {
+            let from = minos[self.below(minos.len())];
+            let next = from.move_direction(Direction::VARIANTS[self.below(4)]);
+            if !minos.contains(&next) {
+                minos.push(next);
+            }
+        }
+
+        Poly {
+            rotatable: self.chance(50),
+            minos,
+        }
+    }
+}
+
+fn random_puzzle(rng: &mut Rng) -> Puzzle {
+    let mut puzzle =
+        Puzzle::default_with_size(rng.below(2) as Coord + 4, rng.below(2) as Coord + 4);
+    puzzle.starts = (0..rng.below(2) + 1).map(|_| rng.vertex(&puzzle)).collect();
+    puzzle.ends = (0..rng.below(2) + 1).map(|_| rng.vertex(&puzzle)).collect();
+    puzzle.starts.dedup();
+    puzzle.ends.dedup();
+
+    for _ in 0..rng.below(10) + 4 {
+        let edge = rng.edge(&puzzle);
+        puzzle.blocked_edges.insert(edge);
+    }
+    for _ in 0..rng.below(4) {
+        let cell = rng.cell(&puzzle);
+        puzzle.outside_positions.insert(cell);
+    }
+
+    // Only use a few kinds of symbols in each puzzle, so that they interact
+    let kinds: Vec<_> = (0..rng.below(2) + 1).map(|_| rng.below(6)).collect();
+    for _ in 0..rng.below(12) + 2 {
+        let cell = rng.cell(&puzzle);
+        let color = rng.below(3) as Color;
+        match kinds[rng.below(kinds.len())] {
+            0 => _ = puzzle.squares.insert(cell, color),
+            1 => _ = puzzle.stars.insert(cell, color),
+            2 => _ = puzzle.triangles.insert(cell, rng.below(3) as u8 + 1),
+            3 => _ = puzzle.polys.insert(cell, rng.poly()),
+            4 => {
+                // Ylops only matter along with polys, so put some next to it
+                _ = puzzle.ylops.insert(cell, rng.poly());
+                for _ in 0..2 {
+                    let dir = Direction::VARIANTS[rng.below(4)];
+                    _ = puzzle.polys.insert(cell.move_direction(dir), rng.poly());
+                }
+            }
+            _ => _ = puzzle.cancels.insert(cell, color),
+        }
+    }
+
+    for _ in 0..rng.below(3) {
+        let stone = rng.vertex(&puzzle);
+        puzzle.vertex_stones.insert(stone);
+    }
+    for _ in 0..rng.below(3) {
+        let stone = rng.edge(&puzzle);
+        puzzle.edge_stones.insert(stone);
+    }
+
+    puzzle
+}
+
+/// Comparable form of a path
+fn key(path: &[Pos]) -> Vec<(Coord, Coord)> {
+    path.iter().map(|pos| (pos.x, pos.y)).collect()
+}
+
+/// Every path of the puzzle that `is_solution` accepts, or `None` if the
+/// puzzle has more than `max_paths` paths
+fn brute_force(puzzle: &Puzzle, max_paths: usize) -> Option<BTreeSet<Vec<(Coord, Coord)>>> {
+    /// Collects the paths to an end, checking them is slow so the puzzles
+    /// with too many paths are given up on first
+    fn extend(
+        puzzle: &Puzzle,
+        path: &mut Vec<Pos>,
+        res: &mut Vec<SolutionPath>,
+        budget: &mut usize,
+    ) -> Option<()> {
+        *budget = budget.checked_sub(1)?;
+        let head = *path.last().unwrap();
+        if puzzle.ends.contains(&head) {
+            res.push(path.clone().into());
+        }
+
+        for dir in Direction::VARIANTS {
+            let next = head.move_direction(dir);
+            if puzzle.contains_vertex(&next)
+                && !puzzle.is_blocked(&EdgePos { pos: head, dir })
+                && !path.contains(&next)
+            {
+                path.push(next);
+                extend(puzzle, path, res, budget)?;
+                path.pop();
+            }
+        }
+        Some(())
+    }
+
+    let mut paths = vec![];
+    let mut budget = max_paths;
+    for start in puzzle.starts.iter() {
+        extend(puzzle, &mut vec![*start], &mut paths, &mut budget)?;
+    }
+    Some(
+        paths
+            .iter()
+            .filter(|path| puzzle.is_solution(path))
+            .map(|path| key(path))
+            .collect(),
+    )
+}
+
+/// Checks that the solver with the features of `idx` finds the expected solutions
+fn check_config(puzzle: &Puzzle, expected: &BTreeSet<Vec<(Coord, Coord)>>, idx: usize, name: &str) {
+    let config = BFSSolverConfig {
+        simple_end_reachability_check: idx & 1 != 0,
+        edge_stones: idx & 2 != 0,
+        partial_area_check: idx & 4 != 0,
+        closed_area_check: idx & 8 != 0,
+        merge_transpositions: idx & 16 != 0,
+        ..Default::default()
+    };
+
+    let mut solver = BFSSolver::new(puzzle).unwrap();
+    solver.config = config;
+    let found: BTreeSet<_> = solver
+        .solve()
+        .solutions
+        .iter()
+        .map(|path| key(path))
+        .collect();
+    assert_eq!(&found, expected, "{name}, config {idx}: {puzzle:?}");
+
+    let count = solver.count().count;
+    assert_eq!(
+        count,
+        expected.len() as u128,
+        "{name}, config {idx}: {puzzle:?}"
+    );
+}
+
+#[test]
+fn test_pruning_keeps_all_solutions() {
+    let mut rng = Rng(0x5eed_1234_abcd_9876);
+    let mut solvable = 0;
+
+    // Unsolvable puzzles are checked too, but only the solvable ones are
+    // counted so that the solutions are actually compared
+    while solvable < PUZZLES {
+        let seed = rng.0;
+        let puzzle = random_puzzle(&mut rng);
+        if puzzle.validate_structure().is_err() {
+            continue;
+        }
+        // Keep the puzzles small enough for the solver without any pruning
+        let Some(expected) = brute_force(&puzzle, MAX_PATHS) else {
+            continue;
+        };
+
+        solvable += !expected.is_empty() as usize;
+        for idx in 0..1 << 5 {
+            check_config(&puzzle, &expected, idx, &format!("seed {seed:#x}"));
+        }
+    }
+}
+
+#[test]
+fn test_partial_areas_around_outside_cells() {
+    // The paths turning around the outside cell used to join the cells on
+    // both sides of it into one partial area, mixing up the squares
+    let mut puzzle = Puzzle::default_with_size(4, 4);
+    puzzle.starts = vec![Pos::new(4, 2)];
+    puzzle.ends = vec![Pos::new(2, 3), Pos::new(0, 2)];
+    puzzle
+        .blocked_edges
+        .insert(EdgePos::new(1, 3, Direction::Right));
+    puzzle.outside_positions.insert(Pos::new(3, 1));
+    puzzle.squares.insert(Pos::new(1, 1), 2);
+    puzzle.squares.insert(Pos::new(3, 0), 1);
+    puzzle.squares.insert(Pos::new(1, 3), 2);
+    let block = Poly {
+        rotatable: true,
+        minos: vec![Pos::new(0, 0)],
+    };
+    let corner = Poly {
+        rotatable: true,
+        minos: vec![Pos::new(0, 0), Pos::new(1, 0), Pos::new(0, 1)],
+    };
+    puzzle.polys.insert(Pos::new(2, 1), corner);
+    puzzle.polys.insert(Pos::new(3, 2), block.clone());
+    puzzle.polys.insert(Pos::new(1, 0), block);
+    puzzle
+        .edge_stones
+        .insert(EdgePos::new(2, 0, Direction::Left));
+    puzzle.edge_stones.insert(EdgePos::new(1, 0, Direction::Up));
+    puzzle
+        .edge_stones
+        .insert(EdgePos::new(2, 2, Direction::Left));
+    puzzle.validate_structure().unwrap();
+
+    let expected = brute_force(&puzzle, usize::MAX).unwrap();
+    assert_eq!(expected.len(), 11);
+    let lost: SolutionPath = "(4, 2) DDLULDLULURRRURULLD".parse().unwrap();
+    assert!(expected.contains(&key(&lost)));
+
+    // With every feature, and with only the partial areas
+    for idx in [(1 << 5) - 1, 4] {
+        check_config(&puzzle, &expected, idx, "outside cell");
+    }
+}